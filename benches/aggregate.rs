@@ -0,0 +1,149 @@
+//! Compares the throughput of a single aggregator shared by all threads
+//! with one aggregator per rayon task which are merged at the end.
+//!
+//! Like the former global result tables, the shared aggregator is only
+//! locked for counting, the lines are parsed before (without the checks of
+//! `ingest` for legacy assets and double `GET`s).
+//!
+//! cargo bench --bench aggregate [-- LINES]
+
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use ohpc_log_analyzer::aggregator::parse_request;
+use ohpc_log_analyzer::{
+    Aggregator,
+    Report,
+    Settings,
+};
+use rayon::prelude::*;
+
+const PATHS: [&str; 6] = [
+    "/OpenHPC/3/EL_9/x86_64/ohpc-release-3-1.el9.x86_64.rpm",
+    "/OpenHPC/3/Leap_15/repodata/repomd.xml",
+    "/OpenHPC/2/CentOS_8/aarch64/slurm-ohpc-22.05.2-1.1.aarch64.rpm",
+    "/OpenHPC/4/EL_10/repodata/repomd.xml",
+    "/ohpc-1.3/CentOS_7/x86_64/OpenHPC-1.3.9.CentOS_7.x86_64.tar",
+    "/OpenHPC/3/openEuler_22.03/noarch/docs-ohpc-3.1-1.noarch.rpm",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const CHUNK_LINES: usize = 10_000;
+
+fn generate(lines: usize) -> Vec<u8> {
+    let mut log = Vec::new();
+    for i in 0..lines {
+        let month = i * 12 / lines;
+        log.extend_from_slice(
+            format!(
+                "10.{}.{}.{} - - [{:02}/{}/2024:{:02}:{:02}:{:02} +0000] \"GET {} HTTP/1.1\" 200 {} \"-\" \"libdnf (Rocky Linux 9.{}; generic; Linux.x86_64)\"\n",
+                i % 7,
+                i % 251,
+                i % 13,
+                1 + i % 28,
+                MONTHS[month],
+                i % 24,
+                i % 60,
+                i % 59,
+                PATHS[i % PATHS.len()],
+                1024 + i % 4096,
+                i % 5,
+            )
+            .as_bytes(),
+        );
+    }
+    log
+}
+
+fn chunks(log: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = log;
+    while !rest.is_empty() {
+        let end = rest
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == b'\n')
+            .nth(CHUNK_LINES - 1)
+            .map_or(rest.len(), |(i, _)| i + 1);
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    chunks
+}
+
+fn shared(settings: &Arc<Settings>, chunks: &[&[u8]]) -> Report {
+    let aggregator = Mutex::new(Aggregator::new(settings.clone()));
+    chunks.par_iter().for_each(|chunk| {
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        for line in chunk.lines() {
+            let request = parse_request(settings, line);
+            aggregator
+                .lock()
+                .unwrap()
+                .count_request(line, request)
+                .unwrap();
+        }
+    });
+    aggregator.into_inner().unwrap().finish()
+}
+
+fn per_thread(settings: &Arc<Settings>, chunks: &[&[u8]]) -> Report {
+    chunks
+        .par_iter()
+        .map(|chunk| {
+            let mut partial = Aggregator::new(settings.clone());
+            partial.ingest(chunk).unwrap();
+            partial
+        })
+        .reduce(
+            || Aggregator::new(settings.clone()),
+            |mut a, b| {
+                a.merge(b);
+                a
+            },
+        )
+        .finish()
+}
+
+fn measure(name: &str, bytes: usize, run: impl Fn() -> Report) -> usize {
+    // Warm up
+    let overall = run().overall;
+    let mut best = Duration::MAX;
+    for _ in 0..3 {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed());
+    }
+    println!(
+        "{name:<12} {:>10.0} lines/s {:>8.1} MB/s ({best:?})",
+        overall as f64 / best.as_secs_f64(),
+        bytes as f64 / 1024.0 / 1024.0 / best.as_secs_f64(),
+    );
+    overall
+}
+
+fn main() {
+    let lines = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse::<usize>().ok())
+        .unwrap_or(200_000);
+    let log = generate(lines);
+    let chunks = chunks(&log);
+    let settings = Arc::new(Settings::default());
+    println!(
+        "{lines} lines, {} chunks, {} threads",
+        chunks.len(),
+        rayon::current_num_threads()
+    );
+
+    let shared = measure("shared", log.len(), || shared(&settings, &chunks));
+    let per_thread = measure("per-thread", log.len(), || per_thread(&settings, &chunks));
+    assert_eq!(shared, per_thread);
+}
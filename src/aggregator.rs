@@ -0,0 +1,1586 @@
+use rustc_hash::{
+    FxHashMap,
+    FxHashSet,
+};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{
+    BTreeMap,
+    HashSet,
+};
+use std::fmt;
+use std::hash::Hash;
+use std::io::Write;
+use std::net::{
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+};
+use std::str::{
+    self,
+    FromStr,
+};
+use std::sync::Arc;
+
+use chrono::{
+    DateTime,
+    Datelike,
+    FixedOffset,
+    NaiveDate,
+    NaiveDateTime,
+    Timelike,
+};
+use regex::bytes::Regex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::config::{
+    Network,
+    Release,
+};
+use crate::parser::{
+    self,
+    Bot,
+    LogRecord,
+    RpmFile,
+};
+use crate::settings::Settings;
+
+/// Reasons why a line of the access log is not counted
+#[derive(Clone, Copy, Debug)]
+pub enum Rejected {
+    Empty,
+    Incomplete,
+    InvalidTimestamp,
+    DoubleGet,
+    StaticAsset,
+}
+
+impl Rejected {
+    pub const ALL: [Rejected; 5] = [
+        Rejected::Empty,
+        Rejected::Incomplete,
+        Rejected::InvalidTimestamp,
+        Rejected::DoubleGet,
+        Rejected::StaticAsset,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rejected::Empty => "empty",
+            Rejected::Incomplete => "incomplete",
+            Rejected::InvalidTimestamp => "invalid_timestamp",
+            Rejected::DoubleGet => "double_get",
+            Rejected::StaticAsset => "static_asset",
+        }
+    }
+}
+
+/// Requests of one client (IP address) within a year or a month
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Client {
+    pub requests: i64,
+    pub size: u64,
+}
+
+/// Requests including the unique clients
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RequestCounts {
+    pub overall: i64,
+    pub unique_overall: i64,
+    pub size: u64,
+    #[serde(with = "entries")]
+    pub ipv4: FxHashMap<u32, Client>,
+    #[serde(with = "entries")]
+    pub ipv6: FxHashMap<u128, Client>,
+}
+
+fn insert_client<K: Eq + Hash>(clients: &mut FxHashMap<K, Client>, ip: K, client: Client) -> bool {
+    match clients.entry(ip) {
+        Entry::Occupied(mut o) => {
+            o.get_mut().requests += client.requests;
+            o.get_mut().size += client.size;
+            false
+        }
+        Entry::Vacant(v) => {
+            v.insert(client);
+            true
+        }
+    }
+}
+
+impl RequestCounts {
+    fn add(&mut self, ip: &IpAddr, size: u64) {
+        self.overall += 1;
+        self.size += size;
+        let client = Client { requests: 1, size };
+        let unique = match ip {
+            IpAddr::V4(ipv4) => insert_client(&mut self.ipv4, (*ipv4).into(), client),
+            IpAddr::V6(ipv6) => insert_client(&mut self.ipv6, (*ipv6).into(), client),
+        };
+        if unique {
+            self.unique_overall += 1;
+        }
+    }
+
+    fn remove(&mut self, ip: &IpAddr) -> Option<Client> {
+        let client = match ip {
+            IpAddr::V4(ipv4) => self.ipv4.remove(&(*ipv4).into()),
+            IpAddr::V6(ipv6) => self.ipv6.remove(&(*ipv6).into()),
+        }?;
+        self.overall -= client.requests;
+        self.size -= client.size;
+        self.unique_overall -= 1;
+        Some(client)
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ipv4) => self.ipv4.contains_key(&(*ipv4).into()),
+            IpAddr::V6(ipv6) => self.ipv6.contains_key(&(*ipv6).into()),
+        }
+    }
+
+    fn merge(&mut self, other: RequestCounts) {
+        self.overall += other.overall;
+        self.size += other.size;
+        for (ip, client) in other.ipv4 {
+            if insert_client(&mut self.ipv4, ip, client) {
+                self.unique_overall += 1;
+            }
+        }
+        for (ip, client) in other.ipv6 {
+            if insert_client(&mut self.ipv6, ip, client) {
+                self.unique_overall += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ResultOverall {
+    pub year: i64,
+    pub counts: RequestCounts,
+}
+
+#[derive(Debug)]
+pub struct ResultOverallPerMonth {
+    pub year: i64,
+    pub month: i64,
+    pub counts: RequestCounts,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ResultPeriod {
+    pub overall: i64,
+    pub unique_overall: i64,
+    pub size: u64,
+    pub ipv4: HashSet<u32>,
+    pub ipv6: HashSet<u128>,
+}
+
+impl ResultPeriod {
+    fn add(&mut self, ip: &IpAddr, size: u64) {
+        self.add_client(ip, Client { requests: 1, size });
+    }
+
+    fn add_client(&mut self, ip: &IpAddr, client: Client) {
+        self.overall += client.requests;
+        self.size += client.size;
+        let unique = match ip {
+            IpAddr::V4(ipv4) => self.ipv4.insert((*ipv4).into()),
+            IpAddr::V6(ipv6) => self.ipv6.insert((*ipv6).into()),
+        };
+        if unique {
+            self.unique_overall += 1;
+        }
+    }
+
+    fn merge(&mut self, other: ResultPeriod) {
+        self.overall += other.overall;
+        self.size += other.size;
+        self.ipv4.extend(other.ipv4);
+        self.ipv6.extend(other.ipv6);
+        self.unique_overall = (self.ipv4.len() + self.ipv6.len()) as i64;
+    }
+}
+
+/// Year or month of the release results
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Period {
+    Year(i64),
+    Month(i64, i64),
+}
+
+impl fmt::Display for Period {
+    /// Years are formatted as `2024` and months as `2024-03`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::Year(year) => write!(f, "{year}"),
+            Period::Month(year, month) => write!(f, "{year}-{month:02}"),
+        }
+    }
+}
+
+/// Identifies the requests of a release, optionally only of one of its
+/// distributions, within a period
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct ReleaseKey {
+    /// Index of the release in the configuration
+    pub release: usize,
+    /// Index of the distribution in the release, `None` for all requests of
+    /// the release
+    pub distribution: Option<usize>,
+    pub period: Period,
+}
+
+/// Repository of a release, or one of its point releases
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum UpdateStream {
+    Base,
+    Updates,
+    /// Point release like `2.6`, the requests are also counted for the base
+    /// or updates repository
+    PointRelease(String),
+}
+
+/// Identifies the requests of a repository or point release of a release
+/// within a period
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct UpdateKey {
+    /// Index of the release in the configuration
+    pub release: usize,
+    pub stream: UpdateStream,
+    pub period: Period,
+}
+
+/// Requests, unique clients and bytes of a repository or point release
+/// within a period, as returned by [`Report::requests_per_update`]
+#[derive(Debug, Serialize)]
+pub struct UpdateRequestsPerPeriod {
+    /// Key of the release
+    pub release: String,
+    /// `base` or `updates`, `None` for the requests of a point release
+    pub repository: Option<&'static str>,
+    pub point_release: Option<String>,
+    pub period: String,
+    pub requests: i64,
+    pub unique: i64,
+    pub size: u64,
+}
+
+/// Identifies the requests of an architecture, optionally only of one
+/// release, within a period
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct ArchitectureKey {
+    /// Index of the architecture in the configuration
+    pub architecture: usize,
+    /// Index of the release in the configuration, `None` for all requests
+    /// of the architecture
+    pub release: Option<usize>,
+    pub period: Period,
+}
+
+/// Requests, unique clients and bytes of an architecture and release within
+/// a period, as returned by [`Report::requests_per_architecture`]
+#[derive(Debug, Serialize)]
+pub struct ArchitectureRequestsPerPeriod {
+    pub architecture: String,
+    /// Key of the release, `None` for all requests of the architecture
+    pub release: Option<String>,
+    pub period: String,
+    pub requests: i64,
+    pub unique: i64,
+    pub size: u64,
+}
+
+/// Requests, unique clients and bytes of a release and distribution within
+/// a period, as returned by [`Report::requests_per_release`]
+#[derive(Debug, Serialize)]
+pub struct ReleaseRequestsPerPeriod {
+    /// Key of the release
+    pub release: String,
+    /// Name of the distribution, `None` for all requests of the release
+    pub distribution: Option<String>,
+    pub period: String,
+    pub requests: i64,
+    pub unique: i64,
+    pub size: u64,
+}
+
+/// Requests, unique clients and bytes of one kind of bot within a period,
+/// as returned by [`Report::bot_traffic`]
+#[derive(Debug, Serialize)]
+pub struct BotRequestsPerPeriod {
+    pub bot: &'static str,
+    pub period: String,
+    pub requests: i64,
+    pub unique: i64,
+    pub size: u64,
+}
+
+/// Requests and bytes of one client within a month, as returned by
+/// [`Report::top_clients`]
+#[derive(Debug, Serialize)]
+pub struct ResultClient {
+    pub period: String,
+    pub ip: String,
+    pub requests: i64,
+    /// Share of all requests of the month
+    pub requests_share: f64,
+    pub size: u64,
+    /// Share of all bytes of the month
+    pub size_share: f64,
+    /// Name of the network of the configuration which contains the client
+    pub network: Option<String>,
+}
+
+/// Requests, unique clients and bytes of a network of the configuration
+/// within a period, as returned by [`Report::requests_per_network`]
+#[derive(Debug, Serialize)]
+pub struct NetworkRequestsPerPeriod {
+    pub network: String,
+    pub excluded: bool,
+    pub period: String,
+    pub requests: i64,
+    pub unique: i64,
+    pub size: u64,
+}
+
+/// Identifies the requests of a client within a period, see
+/// [`parser::parse_user_agent`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UserAgentKey {
+    pub period: Period,
+    /// Index of the release in the configuration, `None` for all requests
+    pub release: Option<usize>,
+    pub client: &'static str,
+    pub version: Option<String>,
+    pub os: Option<String>,
+    pub os_version: Option<String>,
+    pub machine: Option<String>,
+}
+
+/// Requests of a client within a period, as returned by
+/// [`Report::requests_per_user_agent`]
+#[derive(Debug, Serialize)]
+pub struct UserAgentRequestsPerPeriod {
+    pub period: String,
+    /// Key of the release, `None` for all requests
+    pub release: Option<String>,
+    pub client: &'static str,
+    pub version: Option<String>,
+    pub os: Option<String>,
+    pub os_version: Option<String>,
+    pub machine: Option<String>,
+    pub requests: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResultLIBDNF {
+    pub year: i64,
+    pub name: String,
+    pub count: i64,
+}
+
+/// Downloads of one version of a package within a year
+#[derive(Debug)]
+pub struct PackageDownloads {
+    pub year: i64,
+    /// Index of the release in the configuration, `None` for all downloads
+    pub release: Option<usize>,
+    pub name: String,
+    pub version: String,
+    pub count: i64,
+}
+
+/// Downloads of a package (or of one of its versions), as returned by
+/// [`Report::top_packages`]
+#[derive(Debug, Serialize)]
+pub struct ResultPackage {
+    /// `None` for the downloads of all years
+    pub year: Option<i64>,
+    /// Key of the release, `None` for all downloads
+    pub release: Option<String>,
+    pub name: String,
+    /// `None` for the downloads of all versions
+    pub version: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResultType {
+    pub year: i64,
+    pub tar: i64,
+    pub rpm: i64,
+    pub repomd_xml: i64,
+}
+
+/// Length of the periods returned by [`Report::requests_per_period`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    Year,
+    Month,
+    Week,
+    Day,
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "year" => Ok(Granularity::Year),
+            "month" => Ok(Granularity::Month),
+            "week" => Ok(Granularity::Week),
+            "day" => Ok(Granularity::Day),
+            _ => Err(format!(
+                "Unknown granularity '{s}' (expected year, month, week or day)"
+            )),
+        }
+    }
+}
+
+/// Requests and unique clients of one year, month, ISO week or day
+#[derive(Debug, Serialize)]
+pub struct RequestsPerPeriod {
+    pub period: String,
+    pub requests: i64,
+    pub unique: i64,
+    pub size: u64,
+}
+
+/// Final result of an [`Aggregator`]. All tables are sorted by year (and
+/// month or name).
+#[derive(Debug)]
+pub struct Report {
+    /// Releases of the configuration used to count the requests
+    pub releases: Vec<Release>,
+    /// All lines except the empty, double `GET` and legacy asset lines,
+    /// including the rejected lines and the ones outside of the date range
+    pub overall: usize,
+    /// Requests which passed all filters and were counted
+    pub counted_requests: usize,
+    /// Requests per release
+    pub release_requests: Vec<usize>,
+    pub invalid_utf8_lines: usize,
+    pub outside_date_range_lines: usize,
+    pub rejected_lines: BTreeMap<&'static str, usize>,
+    /// Requests per day of the week (Monday first) and hour of the day
+    pub requests_per_weekday_and_hour: Vec<Vec<usize>>,
+    pub overall_results: Vec<ResultOverall>,
+    pub overall_results_per_month: Vec<ResultOverallPerMonth>,
+    pub release_results: BTreeMap<ReleaseKey, ResultPeriod>,
+    /// Architectures of the configuration
+    pub architectures: Vec<String>,
+    pub architecture_results: BTreeMap<ArchitectureKey, ResultPeriod>,
+    pub update_results: BTreeMap<UpdateKey, ResultPeriod>,
+    pub libdnf_results: Vec<ResultLIBDNF>,
+    pub user_agent_results: BTreeMap<UserAgentKey, i64>,
+    /// Operating system names without an alias and the month they were
+    /// first seen in
+    pub unaliased_os_names: BTreeMap<String, Period>,
+    /// Requests of bots, also if they were not excluded
+    pub bot_results: BTreeMap<(Bot, Period), ResultPeriod>,
+    /// Clients per year which only requested the start page or
+    /// `/robots.txt`
+    pub probe_clients: BTreeMap<i64, usize>,
+    /// Networks of the configuration
+    pub networks: Vec<Network>,
+    /// Requests per network name and period, also of excluded networks
+    pub network_results: BTreeMap<(String, Period), ResultPeriod>,
+    pub type_results: Vec<ResultType>,
+    /// Sorted by year, release, name and version
+    pub package_results: Vec<PackageDownloads>,
+    pub daily_results: BTreeMap<NaiveDate, ResultPeriod>,
+    pub weekly_results: BTreeMap<(i32, u32), ResultPeriod>,
+}
+
+impl Report {
+    /// Returns the results of a release (and distribution) within a period
+    pub fn release_result(
+        &self,
+        release: usize,
+        distribution: Option<usize>,
+        period: Period,
+    ) -> Option<&ResultPeriod> {
+        self.release_results.get(&ReleaseKey {
+            release,
+            distribution,
+            period,
+        })
+    }
+
+    /// Returns the most downloaded packages of a year (or of all years if
+    /// `year` is `None`) and release (or of all downloads if `release` is
+    /// `None`). Packages are ranked by name or, if `versions` is true, by
+    /// name and version.
+    pub fn top_packages(
+        &self,
+        year: Option<i64>,
+        release: Option<usize>,
+        versions: bool,
+        limit: usize,
+    ) -> Vec<ResultPackage> {
+        let mut counts: BTreeMap<(&str, Option<&str>), i64> = BTreeMap::new();
+        for package in &self.package_results {
+            if package.release != release || year.is_some_and(|y| y != package.year) {
+                continue;
+            }
+            let version = versions.then_some(package.version.as_str());
+            *counts.entry((&package.name, version)).or_default() += package.count;
+        }
+        let mut counts: Vec<((&str, Option<&str>), i64)> = counts.into_iter().collect();
+        // Most downloads first, equal counts by name
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+            .into_iter()
+            .take(limit)
+            .map(|((name, version), count)| ResultPackage {
+                year,
+                release: release.map(|r| self.releases[r].key.clone()),
+                name: name.to_string(),
+                version: version.map(|v| v.to_string()),
+                count,
+            })
+            .collect()
+    }
+
+    /// Returns the results of a repository or point release of a release
+    /// within a period
+    pub fn update_result(
+        &self,
+        release: usize,
+        stream: UpdateStream,
+        period: Period,
+    ) -> Option<&ResultPeriod> {
+        self.update_results.get(&UpdateKey {
+            release,
+            stream,
+            period,
+        })
+    }
+
+    /// Returns the point releases seen per release, sorted numerically
+    pub fn point_releases(&self) -> Vec<(usize, String)> {
+        let mut point_releases: Vec<(usize, String)> = self
+            .update_results
+            .keys()
+            .filter_map(|key| match &key.stream {
+                UpdateStream::PointRelease(p) => Some((key.release, p.clone())),
+                _ => None,
+            })
+            .collect();
+        point_releases.sort_by_key(|(release, p)| {
+            let version: Vec<u64> = p.split('.').filter_map(|n| n.parse().ok()).collect();
+            (*release, version)
+        });
+        point_releases.dedup();
+        point_releases
+    }
+
+    /// Returns the results of an architecture (and release) within a period
+    pub fn architecture_result(
+        &self,
+        architecture: usize,
+        release: Option<usize>,
+        period: Period,
+    ) -> Option<&ResultPeriod> {
+        self.architecture_results.get(&ArchitectureKey {
+            architecture,
+            release,
+            period,
+        })
+    }
+
+    /// Returns the requests of the base and updates repositories and of the
+    /// point releases of every release per year and month, sorted by
+    /// release, repository (point releases last) and period
+    pub fn requests_per_update(&self) -> Vec<UpdateRequestsPerPeriod> {
+        self.update_results
+            .iter()
+            .map(|(key, r)| {
+                let (repository, point_release) = match &key.stream {
+                    UpdateStream::Base => (Some("base"), None),
+                    UpdateStream::Updates => (Some("updates"), None),
+                    UpdateStream::PointRelease(p) => (None, Some(p.clone())),
+                };
+                UpdateRequestsPerPeriod {
+                    release: self.releases[key.release].key.clone(),
+                    repository,
+                    point_release,
+                    period: key.period.to_string(),
+                    requests: r.overall,
+                    unique: r.unique_overall,
+                    size: r.size,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the clients with the most requests (or, if `by_size` is
+    /// true, the most bytes) within a month
+    pub fn top_clients(
+        &self,
+        year: i64,
+        month: i64,
+        by_size: bool,
+        limit: usize,
+    ) -> Vec<ResultClient> {
+        let counts = match self
+            .overall_results_per_month
+            .iter()
+            .find(|r| r.year == year && r.month == month)
+        {
+            Some(r) => &r.counts,
+            None => return Vec::new(),
+        };
+        let mut clients: Vec<(IpAddr, Client)> = counts
+            .ipv4
+            .iter()
+            .map(|(ip, client)| (IpAddr::V4(Ipv4Addr::from(*ip)), *client))
+            .chain(
+                counts
+                    .ipv6
+                    .iter()
+                    .map(|(ip, client)| (IpAddr::V6(Ipv6Addr::from(*ip)), *client)),
+            )
+            .collect();
+        // Busiest clients first, equal counts by address
+        clients.sort_by(|a, b| match by_size {
+            true => b.1.size.cmp(&a.1.size).then(a.0.cmp(&b.0)),
+            false => b.1.requests.cmp(&a.1.requests).then(a.0.cmp(&b.0)),
+        });
+        let share = |value: f64, total: f64| match total > 0.0 {
+            true => value / total,
+            false => 0.0,
+        };
+        clients
+            .into_iter()
+            .take(limit)
+            .map(|(ip, client)| ResultClient {
+                period: Period::Month(year, month).to_string(),
+                ip: ip.to_string(),
+                requests: client.requests,
+                requests_share: share(client.requests as f64, counts.overall as f64),
+                size: client.size,
+                size_share: share(client.size as f64, counts.size as f64),
+                network: self
+                    .networks
+                    .iter()
+                    .find(|n| n.contains(&ip))
+                    .map(|n| n.name.clone()),
+            })
+            .collect()
+    }
+
+    /// Returns the requests of every network of the configuration per year
+    /// and month, sorted by network and period
+    pub fn requests_per_network(&self) -> Vec<NetworkRequestsPerPeriod> {
+        self.network_results
+            .iter()
+            .map(|((name, period), r)| NetworkRequestsPerPeriod {
+                network: name.clone(),
+                excluded: self.networks.iter().any(|n| n.name == *name && n.exclude),
+                period: period.to_string(),
+                requests: r.overall,
+                unique: r.unique_overall,
+                size: r.size,
+            })
+            .collect()
+    }
+
+    /// Returns the requests of every kind of bot per year and month, sorted
+    /// by bot and period
+    pub fn bot_traffic(&self) -> Vec<BotRequestsPerPeriod> {
+        self.bot_results
+            .iter()
+            .map(|((bot, period), r)| BotRequestsPerPeriod {
+                bot: bot.name(),
+                period: period.to_string(),
+                requests: r.overall,
+                unique: r.unique_overall,
+                size: r.size,
+            })
+            .collect()
+    }
+
+    /// Returns the requests of every client per year and month, for all
+    /// requests and per release, sorted by period, release, client, version
+    /// and operating system
+    pub fn requests_per_user_agent(&self) -> Vec<UserAgentRequestsPerPeriod> {
+        self.user_agent_results
+            .iter()
+            .map(|(key, requests)| UserAgentRequestsPerPeriod {
+                period: key.period.to_string(),
+                release: key.release.map(|r| self.releases[r].key.clone()),
+                client: key.client,
+                version: key.version.clone(),
+                os: key.os.clone(),
+                os_version: key.os_version.clone(),
+                machine: key.machine.clone(),
+                requests: *requests,
+            })
+            .collect()
+    }
+
+    /// Returns the requests of every architecture and release per year and
+    /// month, sorted by architecture, release and period
+    pub fn requests_per_architecture(&self) -> Vec<ArchitectureRequestsPerPeriod> {
+        self.architecture_results
+            .iter()
+            .map(|(key, r)| ArchitectureRequestsPerPeriod {
+                architecture: self.architectures[key.architecture].clone(),
+                release: key.release.map(|i| self.releases[i].key.clone()),
+                period: key.period.to_string(),
+                requests: r.overall,
+                unique: r.unique_overall,
+                size: r.size,
+            })
+            .collect()
+    }
+
+    /// Returns the requests of every release and distribution per year and
+    /// month, sorted by release, distribution and period
+    pub fn requests_per_release(&self) -> Vec<ReleaseRequestsPerPeriod> {
+        self.release_results
+            .iter()
+            .map(|(key, r)| {
+                let release = &self.releases[key.release];
+                ReleaseRequestsPerPeriod {
+                    release: release.key.clone(),
+                    distribution: key
+                        .distribution
+                        .map(|d| release.distributions[d].name.clone()),
+                    period: key.period.to_string(),
+                    requests: r.overall,
+                    unique: r.unique_overall,
+                    size: r.size,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the requests per period in chronological order. Years are
+    /// formatted as `2024`, months as `2024-03`, weeks as `2024-W09` and
+    /// days as `2024-03-01`.
+    pub fn requests_per_period(&self, granularity: Granularity) -> Vec<RequestsPerPeriod> {
+        let period = |period: String, counts: &RequestCounts| RequestsPerPeriod {
+            period,
+            requests: counts.overall,
+            unique: counts.unique_overall,
+            size: counts.size,
+        };
+        match granularity {
+            Granularity::Year => self
+                .overall_results
+                .iter()
+                .map(|r| period(r.year.to_string(), &r.counts))
+                .collect(),
+            Granularity::Month => self
+                .overall_results_per_month
+                .iter()
+                .map(|r| period(format!("{}-{:02}", r.year, r.month), &r.counts))
+                .collect(),
+            Granularity::Week => self
+                .weekly_results
+                .iter()
+                .map(|((year, week), r)| RequestsPerPeriod {
+                    period: format!("{year}-W{week:02}"),
+                    requests: r.overall,
+                    unique: r.unique_overall,
+                    size: r.size,
+                })
+                .collect(),
+            Granularity::Day => self
+                .daily_results
+                .iter()
+                .map(|(day, r)| RequestsPerPeriod {
+                    period: day.format("%Y-%m-%d").to_string(),
+                    requests: r.overall,
+                    unique: r.unique_overall,
+                    size: r.size,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Serializes hash maps as a list of key value pairs, as JSON objects only
+/// support strings as keys
+mod entries {
+    use std::hash::Hash;
+
+    use rustc_hash::FxHashMap;
+    use serde::{
+        Deserialize,
+        Deserializer,
+        Serialize,
+        Serializer,
+    };
+
+    pub fn serialize<K, V, S>(map: &FxHashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<FxHashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+fn double_get() -> Regex {
+    Regex::new(r"(.*GET.*){2,}").unwrap()
+}
+
+/// A parsed line of an access log which is not counted yet
+pub struct Request<'a> {
+    record: LogRecord<'a>,
+    time: DateTime<FixedOffset>,
+    ip: IpAddr,
+}
+
+/// Parses a line of an access log without counting it, see
+/// [`Aggregator::count_request`]
+pub fn parse_request<'a>(settings: &Settings, line: &'a str) -> Result<Request<'a>, Rejected> {
+    let record = match settings.log_format.parse(line) {
+        Some(r) => r,
+        // Skip incomplete lines
+        None => return Err(Rejected::Incomplete),
+    };
+    if settings.assets.is_asset(record.path) {
+        return Err(Rejected::StaticAsset);
+    }
+    let time = match parser::parse_timestamp(record.timestamp) {
+        Some(t) => t,
+        None => return Err(Rejected::InvalidTimestamp),
+    };
+    let ip = match record.ip.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        _ => IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+    };
+    Ok(Request { record, time, ip })
+}
+
+/// Downloads per package name and version
+type PackageVersions = FxHashMap<String, FxHashMap<String, i64>>;
+
+/// Counts the requests of one or more access logs. Every thread uses its
+/// own aggregator and the partial results are merged at the end.
+#[derive(Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Aggregator {
+    #[serde(skip)]
+    settings: Arc<Settings>,
+    #[serde(skip, default = "double_get")]
+    double_get: Regex,
+    counts: Counts,
+}
+
+/// Results of an [`Aggregator`], kept apart from the settings so that the
+/// lines are counted while the settings are borrowed
+#[derive(Default, Deserialize, Serialize)]
+struct Counts {
+    overall: usize,
+    counted: usize,
+    invalid_utf8: usize,
+    outside_date_range: usize,
+    rejected: [usize; Rejected::ALL.len()],
+    hour_and_weekday: [[usize; 24]; 7],
+    #[serde(with = "entries")]
+    overall_results: FxHashMap<i64, RequestCounts>,
+    #[serde(with = "entries")]
+    overall_results_per_month: FxHashMap<(i64, i64), RequestCounts>,
+    #[serde(with = "entries")]
+    release_results: FxHashMap<ReleaseKey, ResultPeriod>,
+    #[serde(with = "entries")]
+    architecture_results: FxHashMap<ArchitectureKey, ResultPeriod>,
+    #[serde(with = "entries")]
+    update_results: FxHashMap<UpdateKey, ResultPeriod>,
+    #[serde(with = "entries")]
+    libdnf_results: FxHashMap<i64, FxHashMap<String, i64>>,
+    /// Requests per period, release and raw user agent, the user agents are
+    /// only classified once per period in [`Aggregator::finish`]
+    #[serde(with = "entries")]
+    user_agent_results: FxHashMap<(Period, Option<usize>), FxHashMap<String, i64>>,
+    #[serde(with = "entries")]
+    type_results: FxHashMap<i64, ResultType>,
+    #[serde(with = "entries")]
+    bot_results: FxHashMap<(Bot, Period), ResultPeriod>,
+    /// Requests per network name of the configuration and period
+    #[serde(with = "entries")]
+    network_results: FxHashMap<(String, Period), ResultPeriod>,
+    /// Year and address of the clients which only requested the start page
+    /// or `/robots.txt` so far, all other counted clients of a year are only
+    /// in `overall_results`
+    probe_clients: FxHashSet<(i64, IpAddr)>,
+    /// Whether bots were not counted, the requests of the probe clients are
+    /// then moved to the bot traffic by [`Aggregator::finish`]
+    exclude_bots: bool,
+    /// Downloads per year and release, package name and version
+    #[serde(with = "entries")]
+    package_results: FxHashMap<(i64, Option<usize>), PackageVersions>,
+    #[serde(with = "entries")]
+    daily_results: FxHashMap<NaiveDate, ResultPeriod>,
+    #[serde(with = "entries")]
+    weekly_results: FxHashMap<(i32, u32), ResultPeriod>,
+}
+
+impl Aggregator {
+    pub fn new(settings: Arc<Settings>) -> Aggregator {
+        Aggregator {
+            settings,
+            double_get: double_get(),
+            counts: Counts::default(),
+        }
+    }
+
+    /// Counts complete lines of an access log. Lines are separated by '\n'
+    /// and a trailing '\n' is optional. Only writing to the rejected lines
+    /// output can fail.
+    pub fn ingest(&mut self, lines: &[u8]) -> std::io::Result<()> {
+        let settings = &*self.settings;
+        if lines.is_empty() {
+            return Ok(());
+        }
+        self.counts.exclude_bots |= settings.exclude_bots;
+        if settings.date_range.skip_chunk(lines, &settings.log_format) {
+            let skipped = lines.split(|c| *c == b'\n').filter(|l| !l.is_empty());
+            let skipped = skipped.count();
+            self.counts.overall += skipped;
+            self.counts.outside_date_range += skipped;
+            return Ok(());
+        }
+        let lines = lines.strip_suffix(b"\n").unwrap_or(lines);
+
+        for line in lines.split(|c| *c == b'\n') {
+            let result = if line.is_empty() {
+                Err(Rejected::Empty)
+            } else if settings.assets.is_legacy_asset(line) {
+                Err(Rejected::StaticAsset)
+            } else if self.double_get.is_match(line) {
+                // Skip broken lines with two or more "GET"s
+                Err(Rejected::DoubleGet)
+            } else {
+                self.counts.process_line(settings, line)
+            };
+            if let Err(reason) = result {
+                self.counts.reject(settings, line, reason)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts a line which was parsed by [`parse_request`], e.g. while the
+    /// aggregator is locked. Unlike [`Aggregator::ingest`], empty lines,
+    /// legacy assets and double `GET`s are not rejected.
+    pub fn count_request(
+        &mut self,
+        line: &str,
+        request: Result<Request<'_>, Rejected>,
+    ) -> std::io::Result<()> {
+        let settings = &*self.settings;
+        self.counts.overall += 1;
+        match request {
+            Ok(request) => self.counts.count(settings, request),
+            Err(reason) => self.counts.reject(settings, line.as_bytes(), reason)?,
+        }
+        Ok(())
+    }
+
+    /// Adds the results of another aggregator. Both aggregators have to
+    /// use the same releases.
+    pub fn merge(&mut self, other: Aggregator) {
+        self.counts.merge(other.counts);
+    }
+
+    /// Replaces the settings, e.g. after restoring a saved aggregator
+    pub fn set_settings(&mut self, settings: Arc<Settings>) {
+        self.settings = settings;
+    }
+
+    /// Number of lines which were not counted, per reason
+    pub fn rejected_lines(&self) -> BTreeMap<&'static str, usize> {
+        Rejected::ALL
+            .iter()
+            .map(|r| (r.name(), self.counts.rejected[*r as usize]))
+            .collect()
+    }
+
+    /// Returns the operating system names in the user agents which are
+    /// neither replaced by an alias of the configuration nor the name of one,
+    /// with the month they were first seen in
+    pub fn unaliased_os_names(&self) -> BTreeMap<String, Period> {
+        let mut names: BTreeMap<String, Period> = BTreeMap::new();
+        for ((period, release), user_agents) in &self.counts.user_agent_results {
+            if release.is_some() || !matches!(period, Period::Month(..)) {
+                continue;
+            }
+            for user_agent in user_agents.keys() {
+                let os = match parser::parse_user_agent(Some(user_agent)).os {
+                    Some(os) if !self.settings.config.is_normalized_os(os) => os,
+                    _ => continue,
+                };
+                match names.get_mut(os) {
+                    Some(first_seen) => *first_seen = (*first_seen).min(*period),
+                    None => {
+                        names.insert(os.to_string(), *period);
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    pub fn finish(self) -> Report {
+        let rejected_lines = self.rejected_lines();
+        let unaliased_os_names = self.unaliased_os_names();
+        let mut counts = self.counts;
+        if counts.exclude_bots {
+            counts.exclude_probe_clients();
+        }
+        let releases = &self.settings.config.releases;
+        let release_requests = (0..releases.len())
+            .map(|release| {
+                counts
+                    .release_results
+                    .iter()
+                    .filter(|(key, _)| {
+                        key.release == release
+                            && key.distribution.is_none()
+                            && matches!(key.period, Period::Year(_))
+                    })
+                    .map(|(_, r)| r.overall as usize)
+                    .sum()
+            })
+            .collect();
+        let mut user_agent_results: BTreeMap<UserAgentKey, i64> = BTreeMap::new();
+        for ((period, release), user_agents) in &counts.user_agent_results {
+            for (user_agent, count) in user_agents {
+                let parsed = parser::parse_user_agent(Some(user_agent));
+                let os = parsed
+                    .os
+                    .map(|os| self.settings.config.os_alias(os).unwrap_or(os));
+                let key = UserAgentKey {
+                    period: *period,
+                    release: *release,
+                    client: parsed.client,
+                    version: parsed.version.map(str::to_string),
+                    os: os.map(str::to_string),
+                    os_version: parsed.os_version.map(str::to_string),
+                    machine: parsed.machine.map(str::to_string),
+                };
+                *user_agent_results.entry(key).or_default() += count;
+            }
+        }
+        // The names are only replaced by their aliases here, like the user
+        // agents above, so that a changed alias applies to the whole state
+        let mut libdnf_results: BTreeMap<(i64, &str), i64> = BTreeMap::new();
+        for (year, names) in &counts.libdnf_results {
+            for (name, count) in names {
+                let name = self.settings.config.os_alias(name).unwrap_or(name);
+                *libdnf_results.entry((*year, name)).or_default() += count;
+            }
+        }
+        let libdnf_results = libdnf_results
+            .into_iter()
+            .map(|((year, name), count)| ResultLIBDNF {
+                year,
+                name: name.to_string(),
+                count,
+            })
+            .collect();
+        let mut probe_clients: BTreeMap<i64, usize> = BTreeMap::new();
+        for (year, _) in &counts.probe_clients {
+            *probe_clients.entry(*year).or_default() += 1;
+        }
+        let mut report = Report {
+            releases: releases.clone(),
+            overall: counts.overall,
+            counted_requests: counts.counted,
+            release_requests,
+            invalid_utf8_lines: counts.invalid_utf8,
+            outside_date_range_lines: counts.outside_date_range,
+            rejected_lines,
+            requests_per_weekday_and_hour: counts
+                .hour_and_weekday
+                .iter()
+                .map(|day| day.to_vec())
+                .collect(),
+            overall_results: counts
+                .overall_results
+                .into_iter()
+                .map(|(year, counts)| ResultOverall { year, counts })
+                .collect(),
+            overall_results_per_month: counts
+                .overall_results_per_month
+                .into_iter()
+                .map(|((year, month), counts)| ResultOverallPerMonth {
+                    year,
+                    month,
+                    counts,
+                })
+                .collect(),
+            release_results: counts.release_results.into_iter().collect(),
+            architectures: self.settings.config.architectures.clone(),
+            architecture_results: counts.architecture_results.into_iter().collect(),
+            update_results: counts.update_results.into_iter().collect(),
+            libdnf_results,
+            user_agent_results,
+            unaliased_os_names,
+            bot_results: counts.bot_results.into_iter().collect(),
+            networks: self.settings.config.networks.clone(),
+            network_results: counts.network_results.into_iter().collect(),
+            probe_clients,
+            type_results: counts.type_results.into_values().collect(),
+            package_results: counts
+                .package_results
+                .into_iter()
+                .flat_map(|((year, release), names)| {
+                    names.into_iter().flat_map(move |(name, versions)| {
+                        versions
+                            .into_iter()
+                            .map(move |(version, count)| PackageDownloads {
+                                year,
+                                release,
+                                name: name.clone(),
+                                version,
+                                count,
+                            })
+                    })
+                })
+                .collect(),
+            daily_results: counts.daily_results.into_iter().collect(),
+            weekly_results: counts.weekly_results.into_iter().collect(),
+        };
+        report.overall_results.sort_unstable_by_key(|r| r.year);
+        report
+            .overall_results_per_month
+            .sort_unstable_by_key(|r| (r.year, r.month));
+        report.type_results.sort_unstable_by_key(|r| r.year);
+        report.package_results.sort_unstable_by(|a, b| {
+            (a.year, a.release, &a.name, &a.version).cmp(&(b.year, b.release, &b.name, &b.version))
+        });
+
+        report
+    }
+}
+
+impl Counts {
+    fn reject(&mut self, settings: &Settings, s: &[u8], reason: Rejected) -> std::io::Result<()> {
+        self.rejected[reason as usize] += 1;
+        if let Some(output) = &settings.rejected_output {
+            let mut writer = output.lock().unwrap();
+            writer.write_all(reason.name().as_bytes())?;
+            writer.write_all(b"\t")?;
+            writer.write_all(s)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn count_type(&mut self, record: &LogRecord, year: i64) {
+        let rpm_found = record.path.ends_with(".rpm");
+        let tar_found = record.path.ends_with(".tar");
+        let repomd_xml_found = record.path.ends_with("/repomd.xml");
+        if !tar_found && !rpm_found && !repomd_xml_found {
+            return;
+        }
+        let result = self.type_results.entry(year).or_insert(ResultType {
+            year,
+            tar: 0,
+            rpm: 0,
+            repomd_xml: 0,
+        });
+        if rpm_found {
+            result.rpm += 1;
+        }
+        if tar_found {
+            result.tar += 1;
+        }
+        if repomd_xml_found {
+            result.repomd_xml += 1;
+        }
+    }
+
+    fn count_libdnf(&mut self, record: &LogRecord, year: i64) {
+        // libdnf (Rocky Linux 9.3; generic; Linux.x86_64)
+        let user_agent_long = match record.user_agent.and_then(|u| u.strip_prefix("libdnf ")) {
+            Some(u) => u,
+            None => return,
+        };
+        let user_agent_short = user_agent_long.split(';').next().unwrap_or_default();
+        let user_agent_short = match user_agent_short.rfind(' ') {
+            Some(i) => &user_agent_short[..i],
+            None => return,
+        };
+        let user_agent = user_agent_short
+            .strip_prefix('(')
+            .unwrap_or(user_agent_short);
+        if user_agent.is_empty() {
+            return;
+        }
+
+        let data = self.libdnf_results.entry(year).or_default();
+        match data.get_mut(user_agent) {
+            Some(count) => *count += 1,
+            None => {
+                data.insert(user_agent.to_string(), 1);
+            }
+        }
+    }
+
+    /// Counts the user agent of a request for all requests (`release` is
+    /// `None`) or for the requests of one release
+    fn count_user_agent(
+        &mut self,
+        record: &LogRecord,
+        periods: [Period; 2],
+        release: Option<usize>,
+    ) {
+        let user_agent = record.user_agent.unwrap_or_default();
+        for period in periods {
+            let data = self
+                .user_agent_results
+                .entry((period, release))
+                .or_default();
+            match data.get_mut(user_agent) {
+                Some(count) => *count += 1,
+                None => {
+                    data.insert(user_agent.to_string(), 1);
+                }
+            }
+        }
+    }
+
+    /// Counts the download of a package for all requests (`release` is
+    /// `None`) or for the requests of one release
+    fn count_package(&mut self, package: &RpmFile, year: i64, release: Option<usize>) {
+        let names = self.package_results.entry((year, release)).or_default();
+        let versions = match names.get_mut(package.name) {
+            Some(v) => v,
+            None => names.entry(package.name.to_string()).or_default(),
+        };
+        match versions.get_mut(package.version) {
+            Some(count) => *count += 1,
+            None => {
+                versions.insert(package.version.to_string(), 1);
+            }
+        }
+    }
+
+    /// Counts a request of an architecture for all requests (`release` is
+    /// `None`) or for the requests of one release
+    fn count_architecture(
+        &mut self,
+        architecture: usize,
+        release: Option<usize>,
+        periods: [Period; 2],
+        ip: &IpAddr,
+        size: u64,
+    ) {
+        for period in periods {
+            self.architecture_results
+                .entry(ArchitectureKey {
+                    architecture,
+                    release,
+                    period,
+                })
+                .or_default()
+                .add(ip, size);
+        }
+    }
+
+    /// Counts the requests per day, per ISO week and per hour of the day
+    /// and day of the week
+    fn count_periods(&mut self, time: &NaiveDateTime, ip: &IpAddr, size: u64) {
+        self.hour_and_weekday[time.weekday().num_days_from_monday() as usize]
+            [time.hour() as usize] += 1;
+        self.daily_results
+            .entry(time.date())
+            .or_default()
+            .add(ip, size);
+        let week = time.iso_week();
+        self.weekly_results
+            .entry((week.year(), week.week()))
+            .or_default()
+            .add(ip, size);
+    }
+
+    fn process_line(&mut self, settings: &Settings, s: &[u8]) -> Result<(), Rejected> {
+        let line = match str::from_utf8(s) {
+            Ok(l) => Cow::Borrowed(l),
+            Err(_) => {
+                // Binary user agents or Latin-1 paths
+                self.invalid_utf8 += 1;
+                String::from_utf8_lossy(s)
+            }
+        };
+        self.overall += 1;
+        let request = parse_request(settings, &line)?;
+        self.count(settings, request);
+        Ok(())
+    }
+
+    fn count(&mut self, settings: &Settings, request: Request) {
+        let Request { record, time, ip } = request;
+        if !settings.date_range.contains(&time) {
+            self.outside_date_range += 1;
+            return;
+        }
+        let time = settings.timezone.local(&time);
+        let year = time.year() as i64;
+        let month = time.month() as i64;
+        let periods = [Period::Year(year), Period::Month(year, month)];
+        let size = record.bytes;
+
+        if let Some(network) = settings.config.network(&ip) {
+            for period in periods {
+                self.network_results
+                    .entry((network.name.clone(), period))
+                    .or_default()
+                    .add(&ip, size);
+            }
+            if network.exclude {
+                return;
+            }
+        }
+        if let Some(bot) = parser::detect_bot(record.user_agent, record.path) {
+            for period in periods {
+                self.bot_results
+                    .entry((bot, period))
+                    .or_default()
+                    .add(&ip, size);
+            }
+            if settings.exclude_bots {
+                return;
+            }
+        }
+
+        if !parser::is_probe_path(record.path) {
+            self.probe_clients.remove(&(year, ip));
+        } else if !self.is_counted(year, &ip) {
+            self.probe_clients.insert((year, ip));
+        }
+
+        self.counted += 1;
+        self.count_libdnf(&record, year);
+        self.count_type(&record, year);
+        self.count_periods(&time, &ip, size);
+
+        self.count_user_agent(&record, periods, None);
+        let architecture = settings.config.architecture(record.path);
+        if let Some(architecture) = architecture {
+            self.count_architecture(architecture, None, periods, &ip, size);
+        }
+        let package = parser::parse_rpm(record.path);
+        if let Some(package) = &package {
+            self.count_package(package, year, None);
+        }
+        let path = record.path.as_bytes();
+        for (index, release) in settings.config.releases.iter().enumerate() {
+            let rest = match release.matches(path) {
+                Some(r) => r,
+                None => continue,
+            };
+            if let Some(architecture) = architecture {
+                self.count_architecture(architecture, Some(index), periods, &ip, size);
+            }
+            if let Some(package) = &package {
+                self.count_package(package, year, Some(index));
+            }
+            self.count_user_agent(&record, periods, Some(index));
+            let update = parser::parse_update_path(str::from_utf8(rest).unwrap_or_default());
+            let repository = match update.updates {
+                true => UpdateStream::Updates,
+                false => UpdateStream::Base,
+            };
+            // Requests of a point release are also counted for its repository
+            let point_release = update
+                .point_release
+                .map(|p| UpdateStream::PointRelease(p.to_string()));
+            for stream in std::iter::once(repository).chain(point_release) {
+                for period in periods {
+                    self.update_results
+                        .entry(UpdateKey {
+                            release: index,
+                            stream: stream.clone(),
+                            period,
+                        })
+                        .or_default()
+                        .add(&ip, size);
+                }
+            }
+            let distribution = release.distribution(rest);
+            for period in periods {
+                // Every request is counted for the release and, if known,
+                // for its distribution
+                for distribution in std::iter::once(None).chain(distribution.map(Some)) {
+                    self.release_results
+                        .entry(ReleaseKey {
+                            release: index,
+                            distribution,
+                            period,
+                        })
+                        .or_default()
+                        .add(&ip, size);
+                }
+            }
+        }
+
+        self.overall_results.entry(year).or_default().add(&ip, size);
+        self.overall_results_per_month
+            .entry((year, month))
+            .or_default()
+            .add(&ip, size);
+    }
+
+    /// Returns true if requests of the client were counted within the year
+    fn is_counted(&self, year: i64, ip: &IpAddr) -> bool {
+        self.overall_results
+            .get(&year)
+            .is_some_and(|r| r.contains(ip))
+    }
+
+    /// Returns false if other requests than probes of the client were
+    /// counted within the year
+    fn may_be_probe_client(&self, key: &(i64, IpAddr)) -> bool {
+        self.probe_clients.contains(key) || !self.is_counted(key.0, &key.1)
+    }
+
+    /// Moves the requests of the probe clients from the yearly and monthly
+    /// results, and so from the top clients, to the bot traffic. The daily,
+    /// weekly and user agent results still include them.
+    fn exclude_probe_clients(&mut self) {
+        for (year, ip) in &self.probe_clients {
+            let results = self.overall_results.get_mut(year);
+            let client = match results.and_then(|r| r.remove(ip)) {
+                Some(client) => client,
+                None => continue,
+            };
+            self.counted -= client.requests as usize;
+            let probe = self.bot_results.entry((Bot::Probe, Period::Year(*year)));
+            probe.or_default().add_client(ip, client);
+            for month in 1..=12 {
+                let results = self.overall_results_per_month.get_mut(&(*year, month));
+                if let Some(client) = results.and_then(|r| r.remove(ip)) {
+                    let period = Period::Month(*year, month);
+                    let probe = self.bot_results.entry((Bot::Probe, period));
+                    probe.or_default().add_client(ip, client);
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Counts) {
+        self.overall += other.overall;
+        self.counted += other.counted;
+        self.exclude_bots |= other.exclude_bots;
+        self.invalid_utf8 += other.invalid_utf8;
+        self.outside_date_range += other.outside_date_range;
+        for (counter, other) in self.rejected.iter_mut().zip(other.rejected) {
+            *counter += other;
+        }
+        for (day, other_day) in self.hour_and_weekday.iter_mut().zip(other.hour_and_weekday) {
+            for (counter, other) in day.iter_mut().zip(other_day) {
+                *counter += other;
+            }
+        }
+
+        // A probe client of one side is none if the other side counted
+        // other requests of it
+        let mut probe_clients = FxHashSet::default();
+        for key in &self.probe_clients {
+            if other.may_be_probe_client(key) {
+                probe_clients.insert(*key);
+            }
+        }
+        for key in &other.probe_clients {
+            if self.may_be_probe_client(key) {
+                probe_clients.insert(*key);
+            }
+        }
+        self.probe_clients = probe_clients;
+        for (year, other) in other.overall_results {
+            self.overall_results.entry(year).or_default().merge(other);
+        }
+        for (year_month, other) in other.overall_results_per_month {
+            self.overall_results_per_month
+                .entry(year_month)
+                .or_default()
+                .merge(other);
+        }
+        for (key, other) in other.release_results {
+            self.release_results.entry(key).or_default().merge(other);
+        }
+        for (key, other) in other.update_results {
+            self.update_results.entry(key).or_default().merge(other);
+        }
+        for (key, other) in other.architecture_results {
+            self.architecture_results
+                .entry(key)
+                .or_default()
+                .merge(other);
+        }
+        for (year, other) in other.libdnf_results {
+            let data = self.libdnf_results.entry(year).or_default();
+            for (name, count) in other {
+                *data.entry(name).or_default() += count;
+            }
+        }
+        for (key, other) in other.user_agent_results {
+            let data = self.user_agent_results.entry(key).or_default();
+            for (user_agent, count) in other {
+                *data.entry(user_agent).or_default() += count;
+            }
+        }
+        for (key, other) in other.bot_results {
+            self.bot_results.entry(key).or_default().merge(other);
+        }
+        for (key, other) in other.network_results {
+            self.network_results.entry(key).or_default().merge(other);
+        }
+        for (year, other) in other.type_results {
+            match self.type_results.entry(year) {
+                Entry::Occupied(mut o) => {
+                    let result = o.get_mut();
+                    result.tar += other.tar;
+                    result.rpm += other.rpm;
+                    result.repomd_xml += other.repomd_xml;
+                }
+                Entry::Vacant(v) => {
+                    v.insert(other);
+                }
+            }
+        }
+        for (key, other) in other.package_results {
+            let names = self.package_results.entry(key).or_default();
+            for (name, other) in other {
+                let versions = names.entry(name).or_default();
+                for (version, count) in other {
+                    *versions.entry(version).or_default() += count;
+                }
+            }
+        }
+        for (day, other) in other.daily_results {
+            self.daily_results.entry(day).or_default().merge(other);
+        }
+        for (week, other) in other.weekly_results {
+            self.weekly_results.entry(week).or_default().merge(other);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Release and distribution rules which are used if no configuration file
+/// is given
+pub const DEFAULT_CONFIG: &str = include_str!("../ohpc-log-analyzer.toml");
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A distribution of a release, recognized by one of its markers in the
+/// request path (e.g. `EL_9`)
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Distribution {
+    pub name: String,
+    pub markers: Vec<String>,
+}
+
+/// A release, recognized by its prefix in the request path (e.g.
+/// `/OpenHPC/3/`)
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Release {
+    /// Name of the release in stats.json
+    pub key: String,
+    /// Name of the release in the plots
+    pub name: String,
+    pub prefix: String,
+    #[serde(default, rename = "distribution")]
+    pub distributions: Vec<Distribution>,
+}
+
+impl Release {
+    /// Returns the part of `path` after the release prefix if the path
+    /// belongs to this release
+    pub fn matches<'a>(&self, path: &'a [u8]) -> Option<&'a [u8]> {
+        find(path, self.prefix.as_bytes()).map(|start| &path[start + self.prefix.len()..])
+    }
+
+    /// Returns the index of the first distribution with a marker in `rest`
+    pub fn distribution(&self, rest: &[u8]) -> Option<usize> {
+        self.distributions
+            .iter()
+            .position(|d| d.markers.iter().any(|m| find(rest, m.as_bytes()).is_some()))
+    }
+}
+
+/// Normalized name of an operating system whose user agents contain one of
+/// the names or match one of the regular expressions
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OsAlias {
+    pub name: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// `patterns`, compiled by [`Config::parse`]
+    #[serde(skip)]
+    regexes: Vec<Regex>,
+}
+
+impl OsAlias {
+    fn matches(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name) || self.regexes.iter().any(|r| r.is_match(name))
+    }
+}
+
+/// Network in CIDR notation, e.g. `192.0.2.0/24` or `2001:db8::/32`
+#[derive(Clone, Copy, Debug)]
+struct Cidr {
+    address: IpAddr,
+    prefix: u32,
+}
+
+impl Cidr {
+    fn parse(s: &str) -> Option<Cidr> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((a, p)) => (a.parse::<IpAddr>().ok()?, Some(p.parse::<u32>().ok()?)),
+            None => (s.parse::<IpAddr>().ok()?, None),
+        };
+        let bits = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(Cidr { address, prefix })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        // Compares the first `prefix` bits of both addresses
+        let matches = |a: u128, b: u128, bits: u32| {
+            let shift = bits - self.prefix;
+            shift == bits || a >> shift == b >> shift
+        };
+        match (self.address, ip) {
+            (IpAddr::V4(a), IpAddr::V4(b)) => {
+                matches(u32::from(a).into(), u32::from(*b).into(), 32)
+            }
+            (IpAddr::V6(a), IpAddr::V6(b)) => matches(a.into(), (*b).into(), 128),
+            _ => false,
+        }
+    }
+}
+
+/// Clients like our own mirrors or CI farms, recognized by their addresses.
+/// Their requests are labeled with the name or, if `exclude` is set, not
+/// counted at all.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Network {
+    pub name: String,
+    /// Addresses in CIDR notation
+    pub cidrs: Vec<String>,
+    #[serde(default)]
+    pub exclude: bool,
+    /// `cidrs`, parsed by [`Config::parse`]
+    #[serde(skip)]
+    ranges: Vec<Cidr>,
+}
+
+impl Network {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.ranges.iter().any(|r| r.contains(ip))
+    }
+}
+
+/// Contents of the configuration file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Architectures which are recognized in the request paths, e.g.
+    /// `x86_64` or `noarch`
+    #[serde(default)]
+    pub architectures: Vec<String>,
+    #[serde(default, rename = "release")]
+    pub releases: Vec<Release>,
+    #[serde(default, rename = "os_alias")]
+    pub os_aliases: Vec<OsAlias>,
+    #[serde(default, rename = "network")]
+    pub networks: Vec<Network>,
+}
+
+impl Config {
+    pub fn parse(s: &str) -> Result<Config, String> {
+        let mut config: Config = toml::from_str(s).map_err(|e| e.to_string())?;
+        let mut architectures = HashSet::new();
+        for architecture in &config.architectures {
+            if architecture.is_empty() || architecture.contains(['/', '.']) {
+                return Err(format!("Invalid architecture '{architecture}'"));
+            }
+            if !architectures.insert(architecture) {
+                return Err(format!("Architecture '{architecture}' is defined twice"));
+            }
+        }
+        let mut keys = HashSet::new();
+        for release in &config.releases {
+            if release.prefix.is_empty() {
+                return Err(format!("Release '{}' has an empty prefix", release.key));
+            }
+            if ["year", "year_month", "overall"].contains(&release.key.as_str()) {
+                return Err(format!("Release key '{}' is reserved", release.key));
+            }
+            if !keys.insert(&release.key) {
+                return Err(format!("Release '{}' is defined twice", release.key));
+            }
+            if release
+                .distributions
+                .iter()
+                .flat_map(|d| &d.markers)
+                .any(|m| m.is_empty())
+            {
+                return Err(format!("Release '{}' has an empty marker", release.key));
+            }
+        }
+        for alias in &mut config.os_aliases {
+            for pattern in &alias.patterns {
+                match Regex::new(pattern) {
+                    Ok(r) => alias.regexes.push(r),
+                    Err(e) => {
+                        return Err(format!(
+                            "Invalid pattern '{pattern}' of the alias '{}': {e}",
+                            alias.name
+                        ))
+                    }
+                }
+            }
+        }
+        for network in &mut config.networks {
+            for cidr in &network.cidrs {
+                match Cidr::parse(cidr) {
+                    Some(c) => network.ranges.push(c),
+                    None => {
+                        return Err(format!(
+                            "Invalid CIDR '{cidr}' of the network '{}'",
+                            network.name
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the first network which contains `ip`
+    pub fn network(&self, ip: &IpAddr) -> Option<&Network> {
+        self.networks.iter().find(|n| n.contains(ip))
+    }
+
+    /// Returns the normalized name of an operating system, `None` if no
+    /// alias matches `name`
+    pub fn os_alias(&self, name: &str) -> Option<&str> {
+        self.os_aliases
+            .iter()
+            .find(|alias| alias.matches(name))
+            .map(|alias| alias.name.as_str())
+    }
+
+    /// Returns whether `name` is the normalized name of an alias or is
+    /// replaced by one
+    pub fn is_normalized_os(&self, name: &str) -> bool {
+        self.os_aliases
+            .iter()
+            .any(|alias| alias.name == name || alias.matches(name))
+    }
+
+    /// Returns the index of the architecture of a request path. The
+    /// architecture of a package (`pdsh-ohpc-2.34-300.ohpc.1.5.x86_64.rpm`)
+    /// or tarball (`OpenHPC-2.2.Leap_15.aarch64.tar`) is taken from its file
+    /// name, otherwise from a directory of the path (`/EL_9/aarch64/`).
+    pub fn architecture(&self, path: &str) -> Option<usize> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let (directories, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+        let position = |name: &str| self.architectures.iter().position(|a| a == name);
+        if let Some(stem) = file_name
+            .strip_suffix(".rpm")
+            .or_else(|| file_name.strip_suffix(".tar"))
+        {
+            if let Some(index) = stem.rsplit_once('.').and_then(|(_, a)| position(a)) {
+                return Some(index);
+            }
+        }
+        directories.split('/').rev().find_map(position)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        Ok(Config::parse(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::parse(DEFAULT_CONFIG).unwrap()
+    }
+}
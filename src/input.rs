@@ -0,0 +1,126 @@
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io::{
+    BufRead,
+    BufReader,
+    Read,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// Returns the position after the last newline of a chunk. Everything
+/// after it is an incomplete line which belongs to the next chunk.
+pub fn last_newline(s: &[u8]) -> usize {
+    let mut i = s.len() - 1;
+    while i > 0 {
+        if s[i] == b'\n' {
+            return i + 1;
+        }
+        i -= 1;
+    }
+    s.len()
+}
+
+/// Opens an access log ('-' is stdin) and transparently decompresses it if
+/// the first bytes match the gzip, bzip2, xz or zstd magic numbers.
+pub fn open_input(input: &str) -> std::io::Result<Box<dyn Read + Send>> {
+    if input == "-" {
+        return decompress(BufReader::new(std::io::stdin()));
+    }
+    decompress(BufReader::new(File::open(input)?))
+}
+
+fn decompress<R: BufRead + Send + 'static>(mut reader: R) -> std::io::Result<Box<dyn Read + Send>> {
+    let magic = reader.fill_buf()?;
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        return Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)));
+    }
+    if magic.starts_with(b"BZh") {
+        return Ok(Box::new(bzip2::bufread::MultiBzDecoder::new(reader)));
+    }
+    if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        return Ok(Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)));
+    }
+    if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return Ok(Box::new(zstd::stream::read::Decoder::with_buffer(reader)?));
+    }
+    Ok(Box::new(reader))
+}
+
+/// Sort key to process rotated logs from the oldest to the newest file,
+/// grouped by the name of the log. Numbered rotations (access_log.2.gz,
+/// access_log.1) come first with the highest number first, followed by
+/// dated rotations (access_log-20240101) and finally the currently active
+/// log file.
+fn rotation_order(path: &Path) -> (PathBuf, String, u8, Reverse<u64>, String) {
+    let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    for extension in [".gz", ".bz2", ".xz", ".zst"] {
+        if let Some(stripped) = name.strip_suffix(extension) {
+            name = stripped.to_string();
+            break;
+        }
+    }
+    if let Some((log, number)) = name.rsplit_once('.') {
+        if let Ok(number) = number.parse::<u64>() {
+            return (directory, log.to_string(), 0, Reverse(number), name);
+        }
+    }
+    if let Some((log, date)) = name.rsplit_once('-') {
+        if !date.is_empty() && date.bytes().all(|c| c.is_ascii_digit()) {
+            return (directory, log.to_string(), 1, Reverse(0), name);
+        }
+    }
+    (directory, name.clone(), 2, Reverse(0), name)
+}
+
+fn walk_directory(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_directory(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expands directories (recursively) and glob patterns given on the
+/// command line into the list of access logs to read.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut expanded: Vec<String> = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        let mut files: Vec<PathBuf> = Vec::new();
+        if input == "-" || path.is_file() {
+            expanded.push(input.clone());
+            continue;
+        } else if path.is_dir() {
+            walk_directory(path, &mut files)?;
+        } else if input.contains(['*', '?', '[']) {
+            for entry in glob::glob(input)? {
+                let entry = entry?;
+                if entry.is_dir() {
+                    walk_directory(&entry, &mut files)?;
+                } else {
+                    files.push(entry);
+                }
+            }
+            if files.is_empty() {
+                return Err(format!("No access log matches '{input}'").into());
+            }
+        } else {
+            return Err(format!("Input '{input}' does not exist").into());
+        }
+        files.sort_by_cached_key(|f| rotation_order(f));
+        expanded.extend(files.iter().map(|f| f.display().to_string()));
+    }
+
+    Ok(expanded)
+}
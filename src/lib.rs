@@ -0,0 +1,42 @@
+//! Parsing and counting of OpenHPC repository access logs.
+//!
+//! An [`Aggregator`] ingests the lines of one or more access logs and
+//! returns a [`Report`] which can be written as HTML and JSON with
+//! [`output::write_report`]. The releases and distributions are recognized
+//! by the rules of a [`Config`].
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use ohpc_log_analyzer::{
+//!     Aggregator,
+//!     Settings,
+//! };
+//!
+//! let mut aggregator = Aggregator::new(Arc::new(Settings::default()));
+//! aggregator
+//!     .ingest(
+//!         b"192.0.2.1 - - [15/Jan/2025:10:00:00 +0000] \"GET /OpenHPC/3/EL_9/repodata/repomd.xml HTTP/1.1\" 200 4096 \"-\" \"libdnf (Rocky Linux 9.5; generic; Linux.x86_64)\"\n",
+//!     )
+//!     .unwrap();
+//! let report = aggregator.finish();
+//! assert_eq!(report.overall, 1);
+//! assert_eq!(report.releases[2].key, "ohpc3");
+//! assert_eq!(report.release_requests[2], 1);
+//! assert_eq!(report.libdnf_results[0].name, "Rocky Linux");
+//! ```
+
+pub mod aggregator;
+pub mod config;
+pub mod input;
+pub mod output;
+pub mod parser;
+pub mod settings;
+pub mod state;
+
+pub use aggregator::{
+    Aggregator,
+    Report,
+};
+pub use config::Config;
+pub use settings::Settings;
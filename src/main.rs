@@ -1,37 +1,30 @@
-extern crate chrono;
 extern crate indicatif;
-extern crate regex;
-extern crate serde;
 
-use rustc_hash::FxHashMap;
-use std::collections::hash_map::Entry;
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{
     Read,
     Write,
 };
-use std::net::{
-    IpAddr,
-    Ipv4Addr,
-    Ipv6Addr,
+use std::path::{
+    Path,
+    PathBuf,
 };
-use std::path::Path;
+use std::process;
 use std::sync::atomic::{
     AtomicUsize,
     Ordering,
 };
 use std::sync::{
     Arc,
-    RwLock,
+    Mutex,
 };
 use std::time::Instant;
-use std::{
-    process,
-    str,
-};
 
-use clap::Parser;
+use clap::{
+    Parser,
+    Subcommand,
+};
 use console::{
     style,
     Emoji,
@@ -40,38 +33,65 @@ use indicatif::{
     ProgressBar,
     ProgressStyle,
 };
-use plotly::layout::{
-    Axis,
-    BarMode,
-    Layout,
+use ohpc_log_analyzer::aggregator::{
+    Granularity,
+    Rejected,
+};
+use ohpc_log_analyzer::input::{
+    expand_inputs,
+    last_newline,
+    open_input,
+};
+use ohpc_log_analyzer::output::{
+    self,
+    OutputOptions,
 };
-use plotly::{
-    Bar,
-    ImageFormat,
-    Plot,
-    Scatter,
+use ohpc_log_analyzer::parser::LogFormat;
+use ohpc_log_analyzer::settings::{
+    AssetFilter,
+    DateRange,
+    ReportingTimezone,
+};
+use ohpc_log_analyzer::state::{
+    State,
+    FINGERPRINT_LENGTH,
+};
+use ohpc_log_analyzer::{
+    Aggregator,
+    Config,
+    Report,
+    Settings,
 };
-use rayon::prelude::*;
-use regex::bytes::Regex;
-use serde::Serialize;
 
-static OVERALL: AtomicUsize = AtomicUsize::new(0);
-static OHPC_1: AtomicUsize = AtomicUsize::new(0);
-static OHPC_2: AtomicUsize = AtomicUsize::new(0);
-static OHPC_3: AtomicUsize = AtomicUsize::new(0);
-static OHPC_4: AtomicUsize = AtomicUsize::new(0);
-static STEPS: AtomicUsize = AtomicUsize::new(3);
+static STEPS: AtomicUsize = AtomicUsize::new(0);
 static CALL_COUNT: AtomicUsize = AtomicUsize::new(1);
-static CHUNK: AtomicUsize = AtomicUsize::new(0);
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
+/// Collects the partial results of the chunks. They are merged in the
+/// order of the chunks, so the saved state does not depend on which thread
+/// finished first.
+struct Partials {
+    next: usize,
+    pending: BTreeMap<usize, Aggregator>,
+    aggregator: Aggregator,
+}
+
+impl Partials {
+    fn add(&mut self, chunk: usize, partial: Aggregator) {
+        self.pending.insert(chunk, partial);
+        while let Some(partial) = self.pending.remove(&self.next) {
+            self.aggregator.merge(partial);
+            self.next += 1;
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct OutputArgs {
     /// Name of the HTML output file
     #[arg(long, default_value = "index.html")]
     html_output: String,
 
-    /// Name of the HTML output file
+    /// Directory of the HTML output file, the svg files and stats.json
     #[arg(long, default_value = "/stats")]
     output_directory: String,
 
@@ -82,719 +102,173 @@ struct Args {
     /// GeoIP mmdb database
     #[arg(long, default_value = "/usr/share/GeoIP/GeoLite2-Country.mmdb")]
     geoip: String,
-
-    /// One or multiple access logs
-    access_log: Vec<String>,
 }
 
-#[derive(Debug)]
-struct ResultOverall {
-    year: i64,
-    ohpc_1: i64,
-    ohpc_2: i64,
-    ohpc_3: i64,
-    ohpc_4: i64,
-    overall: i64,
-    unique_ohpc_1: i64,
-    unique_ohpc_2: i64,
-    unique_ohpc_3: i64,
-    unique_ohpc_4: i64,
-    unique_overall: i64,
-    size: u64,
-    ipv4: HashSet<u32>,
-    ipv6: HashSet<u128>,
-}
+#[derive(clap::Args, Debug)]
+struct InputArgs {
+    /// Access log line format: 'combined', 'common', 'nginx-combined' or an
+    /// Apache LogFormat (e.g. '%h %l %u %t "%r" %>s %b %D')
+    #[arg(long, default_value = "combined")]
+    log_format: String,
+
+    /// Write all rejected lines together with the reason to this file
+    #[arg(long)]
+    rejected_output: Option<String>,
+
+    /// Timezone used to assign requests to years and months ('UTC', an
+    /// offset like '+02:00' or a name like 'Europe/Berlin')
+    #[arg(long, default_value = "UTC", allow_hyphen_values = true)]
+    timezone: String,
+
+    /// Only count requests at or after this date (YYYY-MM-DD in --timezone)
+    /// or RFC 3339 timestamp. Chunks of the logs which are entirely outside
+    /// of the date range are skipped, all their non-empty lines are then
+    /// counted as outside of it, so the rejected line counts are approximate
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only count requests up to and including this date (YYYY-MM-DD in
+    /// --timezone) or before this RFC 3339 timestamp
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Request path extensions which are not counted (static assets)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "png,gif,ico,jpg,jpeg,svg"
+    )]
+    asset_extensions: Vec<String>,
+
+    /// Request path prefixes which are not counted (static assets)
+    #[arg(long, value_delimiter = ',')]
+    asset_prefixes: Vec<String>,
+
+    /// Skip every line containing 'png' or 'gif' anywhere like older
+    /// versions did (only to compare with historical numbers)
+    #[arg(long, default_value = "false")]
+    legacy_asset_filter: bool,
 
-#[derive(Debug)]
-struct ResultOverallPerMonth {
-    year: i64,
-    month: i64,
-    ohpc_1: i64,
-    ohpc_2: i64,
-    ohpc_3: i64,
-    ohpc_4: i64,
-    overall: i64,
-    unique_ohpc_1: i64,
-    unique_ohpc_2: i64,
-    unique_ohpc_3: i64,
-    unique_ohpc_4: i64,
-    unique_overall: i64,
-    size: u64,
-    ipv4: FxHashMap<u32, i64>,
-    ipv6: FxHashMap<u128, i64>,
-}
+    /// Do not count requests of crawlers, monitoring services and scanners
+    /// (they are still reported as bot traffic). Probe clients are moved to
+    /// the bot traffic in the yearly and monthly results
+    #[arg(long, default_value = "false")]
+    exclude_bots: bool,
 
-#[derive(Debug)]
-struct ResultOHPC1 {
-    year: i64,
-    sles: i64,
-    rhel: i64,
+    /// One or multiple access logs, directories or glob patterns ('-' reads from stdin)
+    access_log: Vec<String>,
 }
 
-#[derive(Debug)]
-struct ResultOHPC2 {
-    year: i64,
-    sles: i64,
-    rhel: i64,
+#[derive(clap::Args, Debug)]
+struct ConfigArgs {
+    /// TOML file with the rules to recognize releases and distributions
+    /// (the built-in rules are used if not given)
+    #[arg(long)]
+    config: Option<String>,
 }
 
-#[derive(Debug)]
-struct ResultOHPC3 {
-    year: i64,
-    sles: i64,
-    rhel: i64,
-    openeuler: i64,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Count the access logs and save the results to a state file without
+    /// writing a report
+    Analyze(AnalyzeArgs),
+    /// Write the report (HTML, svg files and stats.json) from a state file
+    Report(ReportArgs),
+    /// Merge the results saved with --state on multiple servers and write
+    /// the report. Clients are counted once even if they used several
+    /// servers.
+    Merge(MergeArgs),
+    /// Print the requests and unique clients per period of a state file
+    Query(QueryArgs),
 }
 
-#[derive(Debug)]
-struct ResultOHPC4 {
-    year: i64,
-    rhel: i64,
-    openeuler: i64,
-}
+#[derive(clap::Args, Debug)]
+struct AnalyzeArgs {
+    #[command(flatten)]
+    input: InputArgs,
 
-#[derive(Debug, Serialize)]
-struct ResultLIBDNF {
-    year: i64,
-    name: String,
-    count: i64,
-}
+    #[command(flatten)]
+    config: ConfigArgs,
 
-#[derive(Debug, Serialize)]
-struct ResultCountry {
-    year: i64,
-    month: i64,
-    country: String,
-    count: i64,
+    /// Load the results of earlier runs from this file (if it exists), skip
+    /// the already counted parts of the access logs and save the results
+    /// to it again
+    #[arg(long)]
+    state: String,
 }
 
-#[derive(Debug)]
-struct ResultType {
-    year: i64,
-    tar: i64,
-    rpm: i64,
-    repomd_xml: i64,
-}
+#[derive(clap::Args, Debug)]
+struct ReportArgs {
+    #[command(flatten)]
+    output: OutputArgs,
 
-#[derive(Serialize)]
-struct UniqueVisitorsPerYear {
-    year: i64,
-    ohpc1: i64,
-    ohpc2: i64,
-    ohpc3: i64,
-    ohpc4: i64,
-    overall: i64,
-}
-#[derive(Serialize)]
-struct UniqueVisitorsPerMonth {
-    year_month: String,
-    ohpc1: i64,
-    ohpc2: i64,
-    ohpc3: i64,
-    ohpc4: i64,
-    overall: i64,
-}
-#[derive(Serialize)]
-struct SizePerYear {
-    year: i64,
-    size: u64,
-}
-#[derive(Serialize)]
-struct SizePerMonth {
-    year_month: String,
-    size: u64,
-}
+    #[command(flatten)]
+    config: ConfigArgs,
 
-#[derive(Serialize)]
-struct Json {
-    unique_visitors_per_year: Vec<UniqueVisitorsPerYear>,
-    unique_visitors_per_month: Vec<UniqueVisitorsPerMonth>,
-    size_per_year: Vec<SizePerYear>,
-    size_per_month: Vec<SizePerMonth>,
-    result_libdnf: Vec<ResultLIBDNF>,
-    result_country: Vec<ResultCountry>,
+    /// State file written by analyze, merge or --state
+    #[arg(long)]
+    state: String,
 }
 
-static OVERALL_RESULTS: RwLock<Vec<ResultOverall>> = RwLock::new(Vec::new());
-static OVERALL_RESULTS_PER_MONTH: RwLock<Vec<ResultOverallPerMonth>> = RwLock::new(Vec::new());
-static OHPC1_RESULTS: RwLock<Vec<ResultOHPC1>> = RwLock::new(Vec::new());
-static OHPC2_RESULTS: RwLock<Vec<ResultOHPC2>> = RwLock::new(Vec::new());
-static OHPC3_RESULTS: RwLock<Vec<ResultOHPC3>> = RwLock::new(Vec::new());
-static OHPC4_RESULTS: RwLock<Vec<ResultOHPC4>> = RwLock::new(Vec::new());
-static LIBDNF_RESULTS: RwLock<Vec<ResultLIBDNF>> = RwLock::new(Vec::new());
-static COUNTRY_RESULTS: RwLock<Vec<ResultCountry>> = RwLock::new(Vec::new());
-static TYPE_RESULTS: RwLock<Vec<ResultType>> = RwLock::new(Vec::new());
-
-static HTML_HEADER: &str =
-    "<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\" /></head><body>   <div>
-<script src=\"https://cdn.jsdelivr.net/npm/mathjax@3.2.2/es5/tex-svg.js\"></script>
-<script src=\"https://cdn.plot.ly/plotly-2.12.1.min.js\"></script>";
-static HTML_FOOTER: &str = "</body></html>";
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    #[command(flatten)]
+    output: OutputArgs,
 
-fn last_newline(s: &[u8]) -> usize {
-    let mut i = s.len() - 1;
-    while i > 0 {
-        if s[i] == b'\n' {
-            return i + 1;
-        }
-        i -= 1;
-    }
-    s.len()
-}
+    #[command(flatten)]
+    config: ConfigArgs,
 
-fn count_type(elements: &[String], year: i64) {
-    if elements.len() < 7 {
-        return;
-    }
-    let rpm_found = elements[6].ends_with(".rpm");
-    let tar_found = elements[6].ends_with(".tar");
-    let repomd_xml_found = elements[6].ends_with("/repomd.xml");
-    if !tar_found && !rpm_found && !repomd_xml_found {
-        return;
-    }
-    let mut data = TYPE_RESULTS.write().unwrap();
-    let mut entry_found = false;
-    for result in data.as_mut_slice() {
-        if result.year == year {
-            entry_found = true;
-            if rpm_found {
-                result.rpm += 1;
-            }
-            if tar_found {
-                result.tar += 1;
-            }
-            if repomd_xml_found {
-                result.repomd_xml += 1;
-            }
-            break;
-        }
-    }
+    /// Also save the merged results to this file
+    #[arg(long)]
+    state: Option<String>,
 
-    if !entry_found {
-        data.push(ResultType {
-            year,
-            tar: tar_found.into(),
-            rpm: rpm_found.into(),
-            repomd_xml: repomd_xml_found.into(),
-        });
-    }
+    /// State files written with --state
+    #[arg(required = true)]
+    states: Vec<String>,
 }
 
-fn count_libdnf(elements: &[String], year: i64) {
-    if elements.len() < 12 {
-        return;
-    }
-    if elements[11] != "\"libdnf" {
-        return;
-    }
-
-    let user_agent_long = elements[12..].join(" ");
-    let user_agent_vec: Vec<_> = user_agent_long.split(';').map(|s| s.to_string()).collect();
-    let mut user_agent_short = user_agent_vec[0].clone();
-    user_agent_short.truncate(user_agent_short.rfind(' ').unwrap());
-    let mut user_agent = &user_agent_short[1..];
-    if user_agent.is_empty() {
-        return;
-    }
-    let change_name = FxHashMap::from_iter([
-        (
-            "Red Hat Enterprise Linux Server",
-            "Red Hat Enterprise Linux",
-        ),
-        ("CentOS AutoSD", "CentOS Linux"),
-        ("CentOS release 8", "CentOS Linux"),
-        ("RockyLinux", "Rocky Linux"),
-    ]);
-    if change_name.contains_key(&user_agent) {
-        user_agent = change_name[&user_agent];
-    }
+#[derive(clap::Args, Debug)]
+struct QueryArgs {
+    #[command(flatten)]
+    config: ConfigArgs,
 
-    let mut data = LIBDNF_RESULTS.write().unwrap();
-    for result in data.as_mut_slice() {
-        if result.year == year && result.name == user_agent {
-            result.count += 1;
-            return;
-        }
-    }
-    data.push(ResultLIBDNF {
-        year,
-        name: user_agent.to_string(),
-        count: 1,
-    });
-}
+    /// State file written by analyze, merge or --state
+    #[arg(long)]
+    state: String,
 
-fn update_distributions_ohpc_3(s: &[u8], year: i64) {
-    let leap_15 = "Leap_15".as_bytes();
-    let el_9 = "EL_9".as_bytes();
-    let openeuler = "openEuler_22.03".as_bytes();
-    let mut leap_15_found = false;
-    let mut el_9_found = false;
-    let mut openeuler_found = false;
-    let search_leap = s
-        .windows(leap_15.len())
-        .position(|window| window == leap_15);
-    if search_leap.is_some() {
-        leap_15_found = true;
-    } else {
-        let search_centos = s.windows(el_9.len()).position(|window| window == el_9);
-        if search_centos.is_some() {
-            el_9_found = true;
-        } else {
-            let search_el = s
-                .windows(openeuler.len())
-                .position(|window| window == openeuler);
-            if search_el.is_some() {
-                openeuler_found = true;
-            }
-        }
-    }
-    if !el_9_found && !leap_15_found && !openeuler_found {
-        return;
-    }
-    let mut data = OHPC3_RESULTS.write().unwrap();
-    let mut year_found = false;
-    for result in &*data {
-        if result.year == year {
-            year_found = true;
-            break;
-        }
-    }
-    if !year_found {
-        data.push(ResultOHPC3 {
-            year,
-            sles: 0,
-            rhel: 0,
-            openeuler: 0,
-        });
-    }
-    for result in data.as_mut_slice() {
-        if result.year == year {
-            if el_9_found {
-                result.rhel += 1;
-            }
-            if leap_15_found {
-                result.sles += 1;
-            }
-            if openeuler_found {
-                result.openeuler += 1;
-            }
-            break;
-        }
-    }
-}
+    /// Length of the periods: 'year', 'month', 'week' or 'day'
+    #[arg(long, default_value = "month")]
+    by: Granularity,
 
-fn update_distributions_ohpc_4(s: &[u8], year: i64) {
-    let el_10 = "EL_10".as_bytes();
-    let openeuler = "openEuler_24.03".as_bytes();
-    let mut el_10_found = false;
-    let mut openeuler_found = false;
-    let search_el = s.windows(el_10.len()).position(|window| window == el_10);
-    if search_el.is_some() {
-        el_10_found = true;
-    } else {
-        let search_openeuler = s
-            .windows(openeuler.len())
-            .position(|window| window == openeuler);
-        if search_openeuler.is_some() {
-            openeuler_found = true;
-        }
-    }
-    if !el_10_found && !openeuler_found {
-        return;
-    }
-    let mut data = OHPC4_RESULTS.write().unwrap();
-    let mut year_found = false;
-    for result in &*data {
-        if result.year == year {
-            year_found = true;
-            break;
-        }
-    }
-    if !year_found {
-        data.push(ResultOHPC4 {
-            year,
-            rhel: 0,
-            openeuler: 0,
-        });
-    }
-    for result in data.as_mut_slice() {
-        if result.year == year {
-            if el_10_found {
-                result.rhel += 1;
-            }
-            if openeuler_found {
-                result.openeuler += 1;
-            }
-            break;
-        }
-    }
-}
+    /// Only print periods starting with this prefix (e.g. '2024' or '2024-03')
+    #[arg(long)]
+    period: Option<String>,
 
-fn month_to_int(s: &str) -> i64 {
-    match s {
-        "Jan" => 1,
-        "Feb" => 2,
-        "Mar" => 3,
-        "Apr" => 4,
-        "May" => 5,
-        "Jun" => 6,
-        "Jul" => 7,
-        "Aug" => 8,
-        "Sep" => 9,
-        "Oct" => 10,
-        "Nov" => 11,
-        "Dec" => 12,
-        _ => 0,
-    }
+    /// Print JSON instead of tab separated values
+    #[arg(long, default_value = "false")]
+    json: bool,
 }
 
-fn process_line(s: &[u8]) {
-    let line = str::from_utf8(s).unwrap();
-    let elements: Vec<_> = line.split(' ').map(|s| s.to_string()).collect();
-    OVERALL.fetch_add(1, Ordering::SeqCst);
-    if elements.len() < 3 {
-        // Skip incomplete lines
-        return;
-    }
-    if elements[3].len() < 8 {
-        return;
-    }
-    let year = match elements[3][8..12].parse::<i64>() {
-        Ok(y) => y,
-        _ => return,
-    };
-    let month_string = &elements[3][4..7];
-    let month = month_to_int(month_string);
-    if month == 0 {
-        return;
-    }
-
-    count_libdnf(&elements, year);
-    count_type(&elements, year);
-    let mut ohpc_1 = false;
-    let mut ohpc_2 = false;
-    let mut ohpc_3 = false;
-    let mut ohpc_4 = false;
-    let substring_1 = "/ohpc-1.3/".as_bytes();
-    let search_1 = s
-        .windows(substring_1.len())
-        .position(|window| window == substring_1);
-    if let Some(search_result) = search_1 {
-        OHPC_1.fetch_add(1, Ordering::SeqCst);
-        ohpc_1 = true;
-        let start = search_result + substring_1.len();
-        let sle_12 = "SLE_12".as_bytes();
-        let centos_7 = "CentOS_7".as_bytes();
-        let mut centos_7_found = false;
-        let mut sle_12_found = false;
-        let search_centos = s[start..]
-            .windows(centos_7.len())
-            .position(|window| window == centos_7);
-        if search_centos.is_some() {
-            centos_7_found = true;
-        } else {
-            let search_sle = s[start..]
-                .windows(sle_12.len())
-                .position(|window| window == sle_12);
-            if search_sle.is_some() {
-                sle_12_found = true;
-            }
-        }
-
-        let mut data = OHPC1_RESULTS.write().unwrap();
-        let mut year_found = false;
-        for result in &*data {
-            if result.year == year {
-                year_found = true;
-                break;
-            }
-        }
-        if !year_found {
-            data.push(ResultOHPC1 {
-                year,
-                sles: 0,
-                rhel: 0,
-            });
-        }
-        for result in data.as_mut_slice() {
-            if result.year == year {
-                if centos_7_found {
-                    result.rhel += 1;
-                }
-                if sle_12_found {
-                    result.sles += 1;
-                }
-                break;
-            }
-        }
-    }
-    let substring_ohpc = "/OpenHPC/".as_bytes();
-    let search_ohpc = s
-        .windows(substring_ohpc.len())
-        .position(|window| window == substring_ohpc);
-
-    if let Some(search_result) = search_ohpc {
-        let substring_2 = "2/".as_bytes();
-        let start = search_result + substring_ohpc.len();
-        if &s[start..start + 2] == substring_2 {
-            let leap_15 = "Leap_15".as_bytes();
-            let centos_8 = "CentOS_8".as_bytes();
-            let el_8 = "EL_8".as_bytes();
-            let mut leap_15_found = false;
-            let mut el_8_found = false;
-            OHPC_2.fetch_add(1, Ordering::SeqCst);
-            ohpc_2 = true;
-            let search_leap = s[start + 2..]
-                .windows(leap_15.len())
-                .position(|window| window == leap_15);
-            if search_leap.is_some() {
-                leap_15_found = true;
-            } else {
-                let search_centos = s[start + 2..]
-                    .windows(centos_8.len())
-                    .position(|window| window == centos_8);
-                if search_centos.is_some() {
-                    el_8_found = true;
-                } else {
-                    let search_el = s[start + 2..]
-                        .windows(el_8.len())
-                        .position(|window| window == el_8);
-                    if search_el.is_some() {
-                        el_8_found = true;
-                    }
-                }
-            }
-            {
-                let mut data = OHPC2_RESULTS.write().unwrap();
-                let mut year_found = false;
-                for result in &*data {
-                    if result.year == year {
-                        year_found = true;
-                        break;
-                    }
-                }
-                if !year_found {
-                    data.push(ResultOHPC2 {
-                        year,
-                        sles: 0,
-                        rhel: 0,
-                    });
-                }
-                for result in data.as_mut_slice() {
-                    if result.year == year {
-                        if el_8_found {
-                            result.rhel += 1;
-                        }
-                        if leap_15_found {
-                            result.sles += 1;
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-        let substring_3 = "3/".as_bytes();
-        if &s[start..start + 2] == substring_3 {
-            OHPC_3.fetch_add(1, Ordering::SeqCst);
-            ohpc_3 = true;
-            update_distributions_ohpc_3(&s[start + 2..], year);
-        }
-        let substring_4 = "4/".as_bytes();
-        if &s[start..start + 2] == substring_4 {
-            OHPC_4.fetch_add(1, Ordering::SeqCst);
-            ohpc_4 = true;
-            update_distributions_ohpc_4(&s[start + 2..], year);
-        }
-    }
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    let size: u64 = match elements.len() < 10 {
-        true => 0,
-        false => elements[9].parse::<u64>().unwrap_or_default(),
-    };
+    #[command(flatten)]
+    output: OutputArgs,
 
-    let ip = match elements[0].parse::<IpAddr>() {
-        Ok(ip) => ip,
-        _ => IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-    };
+    #[command(flatten)]
+    input: InputArgs,
 
-    {
-        let mut data = OVERALL_RESULTS.write().unwrap();
-        let mut year_found = false;
-        for result in &*data {
-            if result.year == year {
-                year_found = true;
-                break;
-            }
-        }
-        if !year_found {
-            data.push(ResultOverall {
-                year,
-                ohpc_1: ohpc_1 as i64,
-                ohpc_2: ohpc_2 as i64,
-                ohpc_3: ohpc_3 as i64,
-                ohpc_4: ohpc_4 as i64,
-                overall: 1,
-                unique_ohpc_1: ohpc_1 as i64,
-                unique_ohpc_2: ohpc_2 as i64,
-                unique_ohpc_3: ohpc_3 as i64,
-                unique_ohpc_4: ohpc_4 as i64,
-                unique_overall: 1,
-                size,
-                ipv4: match &ip {
-                    IpAddr::V4(ipv4) => vec![(*ipv4).into()].into_iter().collect(),
-                    _ => vec![0].into_iter().collect(),
-                },
-                ipv6: match ip {
-                    IpAddr::V6(ipv6) => vec![ipv6.into()].into_iter().collect(),
-                    _ => vec![0].into_iter().collect(),
-                },
-            });
-        } else {
-            for result in data.as_mut_slice() {
-                if result.year == year {
-                    result.overall += 1;
-                    result.size += size;
-                    if ohpc_1 {
-                        result.ohpc_1 += 1;
-                    }
-                    if ohpc_2 {
-                        result.ohpc_2 += 1;
-                    }
-                    if ohpc_3 {
-                        result.ohpc_3 += 1;
-                    }
-                    if ohpc_4 {
-                        result.ohpc_4 += 1;
-                    }
-                    let mut unique = false;
-                    match ip {
-                        IpAddr::V4(ipv4) => {
-                            if !result.ipv4.contains(&ipv4.into()) {
-                                result.ipv4.insert(ipv4.into());
-                                unique = true;
-                            }
-                        }
-                        IpAddr::V6(ipv6) => {
-                            if !result.ipv6.contains(&ipv6.into()) {
-                                result.ipv6.insert(ipv6.into());
-                                unique = true;
-                            }
-                        }
-                    }
-                    if unique {
-                        result.unique_overall += 1;
-                        if ohpc_1 {
-                            result.unique_ohpc_1 += 1;
-                        }
-                        if ohpc_2 {
-                            result.unique_ohpc_2 += 1;
-                        }
-                        if ohpc_3 {
-                            result.unique_ohpc_3 += 1;
-                        }
-                        if ohpc_4 {
-                            result.unique_ohpc_4 += 1;
-                        }
-                    }
-                    break;
-                }
-            }
-        }
-    }
-    {
-        let mut data_year_month = OVERALL_RESULTS_PER_MONTH.write().unwrap();
-        let mut year_month_found = false;
+    #[command(flatten)]
+    config: ConfigArgs,
 
-        for result in &*data_year_month {
-            if result.year == year && result.month == month {
-                year_month_found = true;
-                break;
-            }
-        }
-        if !year_month_found {
-            data_year_month.push(ResultOverallPerMonth {
-                year,
-                month,
-                ohpc_1: ohpc_1 as i64,
-                ohpc_2: ohpc_2 as i64,
-                ohpc_3: ohpc_3 as i64,
-                ohpc_4: ohpc_4 as i64,
-                overall: 1,
-                unique_ohpc_1: ohpc_1 as i64,
-                unique_ohpc_2: ohpc_2 as i64,
-                unique_ohpc_3: ohpc_3 as i64,
-                unique_ohpc_4: ohpc_4 as i64,
-                unique_overall: 1,
-                size,
-                ipv4: match &ip {
-                    IpAddr::V4(ipv4) => FxHashMap::from_iter([((*ipv4).into(), 1)]),
-                    _ => FxHashMap::from_iter([(0, 0)]),
-                },
-                ipv6: match ip {
-                    IpAddr::V6(ipv6) => FxHashMap::from_iter([(ipv6.into(), 1)]),
-                    _ => FxHashMap::from_iter([(0, 0)]),
-                },
-            })
-        } else {
-            for result in data_year_month.as_mut_slice() {
-                if result.year == year && result.month == month {
-                    result.overall += 1;
-                    result.size += size;
-                    if ohpc_1 {
-                        result.ohpc_1 += 1;
-                    }
-                    if ohpc_2 {
-                        result.ohpc_2 += 1;
-                    }
-                    if ohpc_3 {
-                        result.ohpc_3 += 1;
-                    }
-                    if ohpc_4 {
-                        result.ohpc_4 += 1;
-                    }
-                    let mut unique = false;
-                    match ip {
-                        IpAddr::V4(ipv4) => {
-                            if let std::collections::hash_map::Entry::Vacant(e) =
-                                result.ipv4.entry(ipv4.into())
-                            {
-                                e.insert(1);
-                                unique = true;
-                            } else {
-                                *result.ipv4.get_mut(&ipv4.into()).unwrap() += 1;
-                            }
-                        }
-                        IpAddr::V6(ipv6) => {
-                            if let std::collections::hash_map::Entry::Vacant(e) =
-                                result.ipv6.entry(ipv6.into())
-                            {
-                                e.insert(1);
-                                unique = true;
-                            } else {
-                                *result.ipv6.get_mut(&ipv6.into()).unwrap() += 1;
-                            }
-                        }
-                    }
-                    if unique {
-                        result.unique_overall += 1;
-                        if ohpc_1 {
-                            result.unique_ohpc_1 += 1;
-                        }
-                        if ohpc_2 {
-                            result.unique_ohpc_2 += 1;
-                        }
-                        if ohpc_3 {
-                            result.unique_ohpc_3 += 1;
-                        }
-                        if ohpc_4 {
-                            result.unique_ohpc_4 += 1;
-                        }
-                    }
-                    break;
-                }
-            }
-        }
-    }
+    /// Load the results of earlier runs from this file (if it exists), skip
+    /// the already counted parts of the access logs and save the results
+    /// to it again
+    #[arg(long)]
+    state: Option<String>,
 }
 
 pub fn print_step(msg: String) {
@@ -812,56 +286,250 @@ pub fn print_step(msg: String) {
 
 const CHUNK_SIZE: usize = 100_000_000;
 
+/// Skips the first `offset` bytes of an input of which `read` have already
+/// been read into `s`. Returns the number of skipped bytes, which is less
+/// than `offset` if the input is shorter.
+fn skip_processed(access_log: &mut dyn Read, s: &mut Vec<u8>, offset: u64) -> std::io::Result<u64> {
+    if offset <= s.len() as u64 {
+        s.drain(..offset as usize);
+        return Ok(offset);
+    }
+    let read = s.len() as u64;
+    s.clear();
+    let skipped = std::io::copy(&mut access_log.take(offset - read), &mut std::io::sink())?;
+    Ok(read + skipped)
+}
+
 fn main() {
     let start = Instant::now();
     let params = Args::parse();
-    let output = Path::new(&params.output_directory).join(&params.html_output);
-    STEPS.fetch_add(params.access_log.len(), Ordering::SeqCst);
+    match params.command {
+        Some(Command::Analyze(analyze_params)) => {
+            let access_logs = expand(&analyze_params.input.access_log);
+            STEPS.store(2 + access_logs.len(), Ordering::SeqCst);
+            analyze(
+                analyze_params.input,
+                load_config(&analyze_params.config),
+                access_logs,
+                Some(PathBuf::from(analyze_params.state)),
+            );
+            return;
+        }
+        Some(Command::Report(report_params)) => {
+            report(report_params, start);
+            return;
+        }
+        Some(Command::Merge(merge_params)) => {
+            merge(merge_params, start);
+            return;
+        }
+        Some(Command::Query(query_params)) => {
+            query(query_params);
+            return;
+        }
+        None => {}
+    }
+    let output = Path::new(&params.output.output_directory).join(&params.output.html_output);
+    let access_logs = expand(&params.input.access_log);
+    STEPS.store(
+        4 + access_logs.len() + params.state.iter().count(),
+        Ordering::SeqCst,
+    );
     print_step(format!(
         "Using '{}' as output directory",
-        params.output_directory
+        params.output.output_directory
     ));
     print_step(format!("Using '{}' as html output", output.display()));
+    let state = analyze(
+        params.input,
+        load_config(&params.config),
+        access_logs,
+        params.state.as_ref().map(PathBuf::from),
+    );
+    write_report(&state.aggregator.finish(), params.output, start);
+}
+
+fn expand(inputs: &[String]) -> Vec<String> {
+    match expand_inputs(inputs) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("Expanding input files failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn load_config(params: &ConfigArgs) -> Config {
+    let path = match &params.config {
+        Some(p) => p,
+        None => return Config::default(),
+    };
+    match Config::load(Path::new(path)) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("Loading configuration '{path}' failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn load(path: &str, settings: Arc<Settings>) -> State {
+    match State::load(Path::new(path), settings) {
+        Ok(state) => state,
+        Err(e) => {
+            println!("Loading state '{path}' failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Counts the access logs, adding to the results of `state_file` if it
+/// exists, and saves the results to it again
+fn analyze(
+    params: InputArgs,
+    config: Config,
+    access_logs: Vec<String>,
+    state_file: Option<PathBuf>,
+) -> State {
     let spinner_style = ProgressStyle::with_template("{prefix:.bold.dim} {spinner} {wide_msg}")
         .unwrap()
         .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ");
     let pool = rayon::ThreadPoolBuilder::new().build().unwrap();
-    let re = Regex::new(r"(.*GET.*){2,}").unwrap();
+    let log_format = match LogFormat::new(&params.log_format) {
+        Ok(l) => l,
+        Err(e) => {
+            println!("Parsing log format failed: {e}");
+            process::exit(1);
+        }
+    };
+    let timezone = match ReportingTimezone::new(&params.timezone) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("Parsing timezone failed: {e}");
+            process::exit(1);
+        }
+    };
+    let date_range =
+        match DateRange::new(params.since.as_deref(), params.until.as_deref(), &timezone) {
+            Ok(d) => d,
+            Err(e) => {
+                println!("Parsing date range failed: {e}");
+                process::exit(1);
+            }
+        };
+    let rejected_output =
+        params.rejected_output.as_ref().map(|rejected_output| {
+            match File::create(rejected_output) {
+                Ok(f) => Mutex::new(Box::new(std::io::BufWriter::new(f)) as Box<dyn Write + Send>),
+                Err(e) => {
+                    println!("Creating rejected lines file '{rejected_output}' failed: {e}");
+                    process::exit(1);
+                }
+            }
+        });
+    let settings = Arc::new(Settings {
+        log_format,
+        assets: AssetFilter::new(
+            &params.asset_extensions,
+            &params.asset_prefixes,
+            params.legacy_asset_filter,
+        ),
+        timezone,
+        date_range,
+        config,
+        exclude_bots: params.exclude_bots,
+        rejected_output,
+    });
+    let state_exists = state_file.as_ref().is_some_and(|path| path.exists());
+    let mut state = match &state_file {
+        Some(path) if state_exists => match State::load(path, settings.clone()) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Loading state '{}' failed: {e}", path.display());
+                process::exit(1);
+            }
+        },
+        _ => State::new(settings.clone()),
+    };
+    // Without earlier results every unaliased name would be new
+    let os_names = state_exists.then(|| state.aggregator.unaliased_os_names());
+    let partials = Mutex::new(Partials {
+        next: 0,
+        pending: BTreeMap::new(),
+        aggregator: std::mem::replace(&mut state.aggregator, Aggregator::new(settings.clone())),
+    });
+    let mut chunks = 0;
 
-    for input in params.access_log.clone().into_iter() {
+    for input in access_logs.into_iter() {
         let pb = ProgressBar::new(0);
         pb.set_style(spinner_style.clone());
-        let mut access_log = match std::fs::File::open(input.clone()) {
+        let mut access_log = match open_input(&input) {
             Ok(a) => a,
             Err(e) => {
                 println!("Opening input file '{input}' failed: {e}");
                 process::exit(1);
             }
         };
-        print_step(format!("Using '{input:}' as input"));
+        let mut s = Vec::with_capacity(CHUNK_SIZE);
+        if let Err(e) = std::io::Read::by_ref(&mut access_log)
+            .take(FINGERPRINT_LENGTH as u64)
+            .read_to_end(&mut s)
+        {
+            println!("Reading input file '{input}' failed: {e}");
+            process::exit(1);
+        }
+        let prefix = s.clone();
+        let mut offset = match skip_processed(&mut access_log, &mut s, state.processed(&prefix)) {
+            Ok(o) => o,
+            Err(e) => {
+                println!("Reading input file '{input}' failed: {e}");
+                process::exit(1);
+            }
+        };
+        if offset > 0 {
+            print_step(format!(
+                "Using '{input:}' as input (skipping {offset} bytes counted before)"
+            ));
+        } else {
+            print_step(format!("Using '{input:}' as input"));
+        }
 
         pool.scope(|scope| {
-            let mut s = Vec::with_capacity(CHUNK_SIZE);
+            let partials = &partials;
             loop {
-                std::io::Read::by_ref(&mut access_log)
+                if let Err(e) = std::io::Read::by_ref(&mut access_log)
                     .take((CHUNK_SIZE - s.len()) as u64)
                     .read_to_end(&mut s)
-                    .unwrap();
+                {
+                    println!("Reading input file '{input}' failed: {e}");
+                    process::exit(1);
+                }
 
                 if s.is_empty() {
                     // The file has ended.
                     break;
                 }
+                if state_file.is_some() && s.len() < CHUNK_SIZE && s.last() != Some(&b'\n') {
+                    // A growing log may end in a half-written line. It is
+                    // left for the next run instead of counting it and
+                    // moving the saved offset past it.
+                    s.truncate(s.iter().rposition(|c| *c == b'\n').map_or(0, |i| i + 1));
+                    if s.is_empty() {
+                        break;
+                    }
+                }
 
-                CHUNK.fetch_add(CHUNK_SIZE, Ordering::SeqCst);
+                let chunk = chunks;
+                chunks += 1;
                 // Copy any incomplete lines to the next s.
                 let last_newline = last_newline(&s);
                 let mut next_s = Vec::with_capacity(CHUNK_SIZE);
                 next_s.extend_from_slice(&s[last_newline..]);
                 s.truncate(last_newline);
+                offset += last_newline as u64;
                 pb.set_message(format!(
                     "Reading megabytes {}",
-                    CHUNK.load(Ordering::SeqCst) / 1024 / 1024
+                    chunks * CHUNK_SIZE / 1024 / 1024
                 ));
                 pb.inc(1);
 
@@ -874,784 +542,144 @@ fn main() {
 
                 // Move our string into a rayon thread.
                 let data = s;
-                let re = re.clone();
+                let mut partial = Aggregator::new(settings.clone());
                 scope.spawn(move |_| {
-                    let d_s = data[..last_newline].split(|c| *c == b'\n');
-
-                    for i in d_s {
-                        // Skip binary files (images) in a single pass
-                        if i.windows(3).any(|window| window == b"png" || window == b"gif") {
-                            continue;
-                        }
-                        if re.is_match(i) {
-                            // Skip broken lines with two or more "GET"s
-                            continue;
-                        }
-                        process_line(i);
+                    if let Err(e) = partial.ingest(&data) {
+                        println!("Writing rejected line failed: {e}");
+                        process::exit(1);
                     }
+                    partials.lock().unwrap().add(chunk, partial);
                 });
                 s = next_s;
             }
             pb.finish();
         });
+        state.record(&input, &prefix, offset);
     }
-    if let Err(e) = create_plots(params, start) {
-        println!("Error creating diagrams: {e}");
-        process::exit(1);
-    }
-}
-
-fn create_overall_plot() -> String {
-    let labels = vec!["Accesses"];
-    let mut plot = Plot::new();
-    plot.add_trace(
-        Bar::new(labels.clone(), vec![OHPC_1.load(Ordering::SeqCst)]).name("Release 1.3.x"),
-    );
-    plot.add_trace(
-        Bar::new(labels.clone(), vec![OHPC_2.load(Ordering::SeqCst)]).name("Release 2.x"),
-    );
-    plot.add_trace(
-        Bar::new(labels.clone(), vec![OHPC_3.load(Ordering::SeqCst)]).name("Release 3.x"),
-    );
-    plot.add_trace(
-        Bar::new(labels.clone(), vec![OHPC_4.load(Ordering::SeqCst)]).name("Release 4.x"),
-    );
-    plot.add_trace(
-        Bar::new(
-            labels,
-            vec![
-                OVERALL.load(Ordering::SeqCst)
-                    - OHPC_1.load(Ordering::SeqCst)
-                    - OHPC_2.load(Ordering::SeqCst)
-                    - OHPC_3.load(Ordering::SeqCst)
-                    - OHPC_4.load(Ordering::SeqCst),
-            ],
-        )
-        .name("Other"),
-    );
-    plot.set_layout(
-        Layout::new()
-            .bar_mode(BarMode::Stack)
-            .title("OHPC overall repository accesses"),
-    );
-
-    plot.to_inline_html(None)
-}
-
-fn create_type_plot() -> String {
-    let mut years: Vec<i64> = Vec::new();
-    let mut ticks: Vec<f64> = Vec::new();
-    let data = TYPE_RESULTS.read().unwrap();
-    for result in &*data {
-        years.push(result.year);
-    }
-    years.sort_unstable();
-
-    let mut tar: Vec<i64> = Vec::new();
-    let mut rpm: Vec<i64> = Vec::new();
-    let mut repomd_xml: Vec<i64> = Vec::new();
-    for year in &years {
-        for result in &*data {
-            if result.year == *year {
-                tar.push(result.tar);
-                rpm.push(result.rpm);
-                repomd_xml.push(result.repomd_xml);
-                ticks.push((*year) as f64);
-                break;
-            }
+    if let Some(writer) = &settings.rejected_output {
+        if let Err(e) = writer.lock().unwrap().flush() {
+            println!("Writing rejected lines failed: {e}");
+            process::exit(1);
         }
     }
-
-    let mut plot = Plot::new();
-    plot.add_trace(Scatter::new(years.clone(), tar).name("TAR"));
-    plot.add_trace(Scatter::new(years.clone(), rpm).name("RPM"));
-    plot.add_trace(Scatter::new(years.clone(), repomd_xml).name("repomd.xml"));
-
-    plot.set_layout(
-        Layout::new()
-            .title("OHPC file types per year")
-            .x_axis(Axis::new().tick_values(ticks.clone())),
-    );
-
-    plot.to_inline_html(None)
-}
-
-fn get_years(years: &mut Vec<i64>) -> Result<(), Box<dyn std::error::Error>> {
-    let data = OVERALL_RESULTS.read()?;
-    for result in &*data {
-        years.push(result.year);
-    }
-    years.sort_unstable();
-
-    Ok(())
-}
-
-fn create_repository_requests_per_year(
-    years: &Vec<i64>,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut ohpc_1: Vec<i64> = Vec::new();
-    let mut ohpc_2: Vec<i64> = Vec::new();
-    let mut ohpc_3: Vec<i64> = Vec::new();
-    let mut ohpc_4: Vec<i64> = Vec::new();
-    let mut overall: Vec<i64> = Vec::new();
-    let mut ticks: Vec<f64> = Vec::new();
-    let data = OVERALL_RESULTS.read()?;
-
-    for year in years {
-        for result in &*data {
-            if result.year == *year {
-                ohpc_1.push(result.ohpc_1);
-                ohpc_2.push(result.ohpc_2);
-                ohpc_3.push(result.ohpc_3);
-                ohpc_4.push(result.ohpc_4);
-                overall.push(result.overall);
-                ticks.push((*year) as f64);
-                break;
+    state.aggregator = partials.into_inner().unwrap().aggregator;
+    if let Some(os_names) = os_names {
+        for (name, first_seen) in state.aggregator.unaliased_os_names() {
+            if !os_names.contains_key(&name) {
+                println!("New operating system name '{name}' first seen in {first_seen}");
             }
         }
     }
-
-    let mut plot = Plot::new();
-    plot.add_trace(Scatter::new(years.clone(), ohpc_1).name("OHPC 1.3.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_2).name("OHPC 2.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_3).name("OHPC 3.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_4).name("OHPC 4.x"));
-    plot.add_trace(Scatter::new(years.clone(), overall).name("Total"));
-    plot.set_layout(
-        Layout::new()
-            .title("OHPC repository requests per year")
-            .x_axis(Axis::new().tick_values(ticks.clone())),
-    );
-
-    Ok(plot.to_inline_html(None))
-}
-
-fn create_unique_repository_requests_per_year(
-    years: &Vec<i64>,
-    params: &Args,
-    json: &mut Json,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut unique_ohpc_1: Vec<i64> = Vec::new();
-    let mut unique_ohpc_2: Vec<i64> = Vec::new();
-    let mut unique_ohpc_3: Vec<i64> = Vec::new();
-    let mut unique_ohpc_4: Vec<i64> = Vec::new();
-    let mut unique_overall: Vec<i64> = Vec::new();
-    let mut ticks: Vec<f64> = Vec::new();
-
-    let data = OVERALL_RESULTS.read()?;
-    for year in years {
-        for result in &*data {
-            if result.year == *year {
-                unique_ohpc_1.push(result.unique_ohpc_1);
-                unique_ohpc_2.push(result.unique_ohpc_2);
-                unique_ohpc_3.push(result.unique_ohpc_3);
-                unique_ohpc_4.push(result.unique_ohpc_4);
-                unique_overall.push(result.unique_overall);
-                ticks.push((*year) as f64);
-                json.unique_visitors_per_year.push(UniqueVisitorsPerYear {
-                    year: *year,
-                    ohpc1: result.unique_ohpc_1,
-                    ohpc2: result.unique_ohpc_2,
-                    ohpc3: result.unique_ohpc_3,
-                    ohpc4: result.unique_ohpc_4,
-                    overall: result.unique_overall,
-                });
-                break;
-            }
-        }
-    }
-
-    let mut unique_plot = Plot::new();
-    let trace_unique_ohpc_1 = Scatter::new(years.clone(), unique_ohpc_1).name("OHPC 1.3.x");
-    let trace_unique_ohpc_2 = Scatter::new(years.clone(), unique_ohpc_2).name("OHPC 2.x");
-    let trace_unique_ohpc_3 = Scatter::new(years.clone(), unique_ohpc_3).name("OHPC 3.x");
-    let trace_unique_ohpc_4 = Scatter::new(years.clone(), unique_ohpc_4).name("OHPC 4.x");
-    let trace_unique_overall = Scatter::new(years.clone(), unique_overall).name("Total");
-    unique_plot.add_trace(trace_unique_ohpc_1);
-    unique_plot.add_trace(trace_unique_ohpc_2);
-    unique_plot.add_trace(trace_unique_ohpc_3);
-    unique_plot.add_trace(trace_unique_ohpc_4);
-    unique_plot.add_trace(trace_unique_overall);
-    let unique_layout = Layout::new()
-        .title("Unique OHPC repository requests per year")
-        .x_axis(Axis::new().tick_values(ticks.clone()));
-    unique_plot.set_layout(unique_layout);
-    if !params.no_svg {
-        unique_plot.write_image(
-            Path::new(&params.output_directory).join("unique_visitors_per_year.svg"),
-            ImageFormat::SVG,
-            1600,
-            600,
-            1.0,
-        );
-    }
-
-    Ok(unique_plot.to_inline_html(None))
-}
-
-fn create_data_downloaded_per_year(
-    years: &Vec<i64>,
-    params: &Args,
-    json: &mut Json,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut ticks: Vec<f64> = Vec::new();
-    let mut size: Vec<u64> = Vec::new();
-    let data = OVERALL_RESULTS.read()?;
-
-    for year in years {
-        for result in &*data {
-            if result.year == *year {
-                size.push(result.size);
-                ticks.push((*year) as f64);
-                json.size_per_year.push(SizePerYear {
-                    year: *year,
-                    size: result.size,
-                });
-                break;
-            }
+    if let Some(path) = &state_file {
+        if let Err(e) = state.save(path) {
+            println!("Saving state '{}' failed: {e}", path.display());
+            process::exit(1);
         }
+        print_step(format!("Saved state to '{}'", path.display()));
     }
-
-    let mut plot_size_per_year = Plot::new();
-    let size_per_year = Scatter::new(years.clone(), size).name("Total");
-    plot_size_per_year.add_trace(size_per_year);
-    let layout_size_per_year = Layout::new()
-        .title("OHPC data downloaded per year")
-        .x_axis(Axis::new().tick_values(ticks.clone()));
-    plot_size_per_year.set_layout(layout_size_per_year);
-
-    if !params.no_svg {
-        plot_size_per_year.write_image(
-            Path::new(&params.output_directory).join("size_per_year.svg"),
-            ImageFormat::SVG,
-            1600,
-            600,
-            1.0,
-        );
-    }
-
-    Ok(plot_size_per_year.to_inline_html(None))
+    let rejected_lines = state.aggregator.rejected_lines();
+    print_step(format!(
+        "Rejected lines: {}",
+        Rejected::ALL
+            .iter()
+            .map(|r| format!("{} {}", r.name(), rejected_lines[r.name()]))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    state
 }
 
-fn create_repository_requests_per_month() -> Result<String, Box<dyn std::error::Error>> {
-    let mut ohpc_1_per_month: Vec<i64> = Vec::new();
-    let mut ohpc_2_per_month: Vec<i64> = Vec::new();
-    let mut ohpc_3_per_month: Vec<i64> = Vec::new();
-    let mut ohpc_4_per_month: Vec<i64> = Vec::new();
-    let mut overall_per_month: Vec<i64> = Vec::new();
-    let mut year_months: Vec<String> = Vec::new();
-
-    let data = OVERALL_RESULTS_PER_MONTH.read()?;
-    for result in &*data {
-        year_months.push(format!("{}-{:02}", result.year, result.month));
-    }
-    year_months.sort_unstable();
-
-    for year_month in &year_months {
-        for result in &*data {
-            if format!("{}-{:02}", result.year, result.month) == *year_month {
-                ohpc_1_per_month.push(result.ohpc_1);
-                ohpc_2_per_month.push(result.ohpc_2);
-                ohpc_3_per_month.push(result.ohpc_3);
-                ohpc_4_per_month.push(result.ohpc_4);
-                overall_per_month.push(result.overall);
-                break;
-            }
-        }
+fn write_report(report: &Report, output: OutputArgs, start: Instant) {
+    let options = OutputOptions {
+        output_directory: output.output_directory,
+        html_output: output.html_output,
+        no_svg: output.no_svg,
+        geoip: output.geoip,
+    };
+    if let Err(e) = output::write_report(report, &options, start) {
+        println!("Error creating diagrams: {e}");
+        process::exit(1);
     }
-
-    let mut plot_overall_per_month = Plot::new();
-    plot_overall_per_month
-        .add_trace(Scatter::new(year_months.clone(), ohpc_1_per_month).name("OHPC 1.3.x"));
-    plot_overall_per_month
-        .add_trace(Scatter::new(year_months.clone(), ohpc_2_per_month).name("OHPC 2.x"));
-    plot_overall_per_month
-        .add_trace(Scatter::new(year_months.clone(), ohpc_3_per_month).name("OHPC 3.x"));
-    plot_overall_per_month
-        .add_trace(Scatter::new(year_months.clone(), ohpc_4_per_month).name("OHPC 4.x"));
-    plot_overall_per_month
-        .add_trace(Scatter::new(year_months.clone(), overall_per_month).name("Total"));
-    plot_overall_per_month
-        .set_layout(Layout::new().title("OHPC repository requests per month"));
-
-    Ok(plot_overall_per_month.to_inline_html(None))
+    print_step(format!(
+        "Finished after {:?} at {} ({} lines with invalid UTF-8)",
+        start.elapsed(),
+        chrono::Utc::now().to_rfc2822(),
+        report.invalid_utf8_lines
+    ));
 }
 
-fn create_unique_repository_requests_per_month(
-    params: &Args,
-    json: &mut Json,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut unique_ohpc_1_per_month: Vec<i64> = Vec::new();
-    let mut unique_ohpc_2_per_month: Vec<i64> = Vec::new();
-    let mut unique_ohpc_3_per_month: Vec<i64> = Vec::new();
-    let mut unique_ohpc_4_per_month: Vec<i64> = Vec::new();
-    let mut unique_overall_per_month: Vec<i64> = Vec::new();
-    let mut year_months: Vec<String> = Vec::new();
-
-    let data = OVERALL_RESULTS_PER_MONTH.read()?;
-    for result in &*data {
-        year_months.push(format!("{}-{:02}", result.year, result.month));
-    }
-    year_months.sort_unstable();
-
-    for year_month in &year_months {
-        for result in &*data {
-            if format!("{}-{:02}", result.year, result.month) == *year_month {
-                unique_ohpc_1_per_month.push(result.unique_ohpc_1);
-                unique_ohpc_2_per_month.push(result.unique_ohpc_2);
-                unique_ohpc_3_per_month.push(result.unique_ohpc_3);
-                unique_ohpc_4_per_month.push(result.unique_ohpc_4);
-                unique_overall_per_month.push(result.unique_overall);
-                json.unique_visitors_per_month.push(UniqueVisitorsPerMonth {
-                    year_month: year_month.clone(),
-                    ohpc1: result.unique_ohpc_1,
-                    ohpc2: result.unique_ohpc_2,
-                    ohpc3: result.unique_ohpc_3,
-                    ohpc4: result.unique_ohpc_4,
-                    overall: result.unique_overall,
-                });
-                break;
-            }
-        }
-    }
-
-    let mut plot = Plot::new();
-    plot.add_trace(Scatter::new(year_months.clone(), unique_ohpc_1_per_month).name("OHPC 1.3.x"));
-    plot.add_trace(Scatter::new(year_months.clone(), unique_ohpc_2_per_month).name("OHPC 2.x"));
-    plot.add_trace(Scatter::new(year_months.clone(), unique_ohpc_3_per_month).name("OHPC 3.x"));
-    plot.add_trace(Scatter::new(year_months.clone(), unique_ohpc_4_per_month).name("OHPC 4.x"));
-    plot.add_trace(Scatter::new(year_months.clone(), unique_overall_per_month).name("Total"));
-    plot.set_layout(Layout::new().title("Unique OHPC repository requests per month"));
-
-    if !params.no_svg {
-        plot.write_image(
-            Path::new(&params.output_directory).join("unique_visitors_per_month.svg"),
-            ImageFormat::SVG,
-            1600,
-            600,
-            1.0,
-        );
-    }
-
-    Ok(plot.to_inline_html(None))
+/// Writes the report of a state file
+fn report(params: ReportArgs, start: Instant) {
+    let output = Path::new(&params.output.output_directory).join(&params.output.html_output);
+    STEPS.store(4, Ordering::SeqCst);
+    print_step(format!(
+        "Using '{}' as output directory",
+        params.output.output_directory
+    ));
+    print_step(format!("Using '{}' as html output", output.display()));
+    let settings = Arc::new(Settings {
+        config: load_config(&params.config),
+        ..Default::default()
+    });
+    let state = load(&params.state, settings);
+    print_step(format!("Loaded state '{}'", params.state));
+    write_report(&state.aggregator.finish(), params.output, start);
 }
 
-fn create_repository_requests_per_year_and_distribution(
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut ohpc_1_sles: Vec<i64> = Vec::new();
-    let mut ohpc_1_rhel: Vec<i64> = Vec::new();
-    let data_ohpc_1 = OHPC1_RESULTS.read()?;
-    let mut years = Vec::new();
-    let mut ticks: Vec<f64> = Vec::new();
-
-    for result in &*data_ohpc_1 {
-        years.push(result.year);
-    }
-    years.sort_unstable();
-    for year in &years {
-        for result in &*data_ohpc_1 {
-            if result.year == *year {
-                ohpc_1_sles.push(result.sles);
-                ohpc_1_rhel.push(result.rhel);
-                ticks.push((*year) as f64);
-                break;
-            }
-        }
-    }
-    let mut plot = Plot::new();
-    plot.add_trace(Scatter::new(years.clone(), ohpc_1_sles).name("OHPC SLES 1.3.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_1_rhel).name("OHPC RHEL 1.3.x"));
-    plot.set_layout(
-        Layout::new()
-            .title("OHPC repository requests per year and distribution")
-            .x_axis(Axis::new().tick_values(ticks.clone())),
+/// Merges the state files of multiple servers into one report
+fn merge(params: MergeArgs, start: Instant) {
+    let output = Path::new(&params.output.output_directory).join(&params.output.html_output);
+    STEPS.store(
+        3 + params.states.len() + params.state.iter().count(),
+        Ordering::SeqCst,
     );
-
-    let mut ohpc_2_sles: Vec<i64> = Vec::new();
-    let mut ohpc_2_rhel: Vec<i64> = Vec::new();
-    let data_ohpc_2 = OHPC2_RESULTS.read()?;
-    years = Vec::new();
-    for result in &*data_ohpc_2 {
-        years.push(result.year);
-    }
-    years.sort_unstable();
-
-    for year in &years {
-        for result in &*data_ohpc_2 {
-            if result.year == *year {
-                ohpc_2_sles.push(result.sles);
-                ohpc_2_rhel.push(result.rhel);
-                break;
-            }
-        }
-    }
-    plot.add_trace(Scatter::new(years.clone(), ohpc_2_sles).name("OHPC SLES 2.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_2_rhel).name("OHPC RHEL 2.x"));
-
-    let mut ohpc_3_sles: Vec<i64> = Vec::new();
-    let mut ohpc_3_rhel: Vec<i64> = Vec::new();
-    let mut ohpc_3_openeuler: Vec<i64> = Vec::new();
-    let data_ohpc_3 = OHPC3_RESULTS.read().unwrap();
-    years = Vec::new();
-    for result in &*data_ohpc_3 {
-        years.push(result.year);
-    }
-    years.sort_unstable();
-    for year in &years {
-        for result in &*data_ohpc_3 {
-            if result.year == *year {
-                ohpc_3_sles.push(result.sles);
-                ohpc_3_rhel.push(result.rhel);
-                ohpc_3_openeuler.push(result.openeuler);
-                break;
-            }
-        }
-    }
-    plot.add_trace(Scatter::new(years.clone(), ohpc_3_sles).name("OHPC SLES 3.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_3_rhel).name("OHPC RHEL 3.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_3_openeuler).name("OHPC openEuler 3.x"));
-
-    let mut ohpc_4_rhel: Vec<i64> = Vec::new();
-    let mut ohpc_4_openeuler: Vec<i64> = Vec::new();
-    let data_ohpc_4 = OHPC4_RESULTS.read().unwrap();
-    years = Vec::new();
-    for result in &*data_ohpc_4 {
-        years.push(result.year);
-    }
-    years.sort_unstable();
-    for year in &years {
-        for result in &*data_ohpc_4 {
-            if result.year == *year {
-                ohpc_4_rhel.push(result.rhel);
-                ohpc_4_openeuler.push(result.openeuler);
-                break;
-            }
-        }
-    }
-    plot.add_trace(Scatter::new(years.clone(), ohpc_4_rhel).name("OHPC RHEL 4.x"));
-    plot.add_trace(Scatter::new(years.clone(), ohpc_4_openeuler).name("OHPC openEuler 4.x"));
-
-    Ok(plot.to_inline_html(None))
-}
-
-fn create_data_downloaded_per_month(
-    params: &Args,
-    json: &mut Json,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut size_per_month: Vec<u64> = Vec::new();
-    let mut year_months: Vec<String> = Vec::new();
-
-    let data = OVERALL_RESULTS_PER_MONTH.read()?;
-    for result in &*data {
-        year_months.push(format!("{}-{:02}", result.year, result.month));
-    }
-    year_months.sort_unstable();
-
-    for year_month in &year_months {
-        for result in &*data {
-            if format!("{}-{:02}", result.year, result.month) == *year_month {
-                size_per_month.push(result.size);
-                json.size_per_month.push(SizePerMonth {
-                    year_month: year_month.clone(),
-                    size: result.size,
-                });
-                break;
-            }
-        }
-    }
-
-    let mut plot = Plot::new();
-    plot.add_trace(Scatter::new(year_months.clone(), size_per_month).name("Total"));
-    plot.set_layout(Layout::new().title("OHPC data downloaded per month"));
-
-    if !params.no_svg {
-        plot.write_image(
-            Path::new(&params.output_directory).join("size_per_month.svg"),
-            ImageFormat::SVG,
-            1600,
-            600,
-            1.0,
-        );
-    }
-
-    Ok(plot.to_inline_html(None))
-}
-
-fn fill_country_results(params: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    let geoip_reader = match maxminddb::Reader::open_readfile(&params.geoip) {
-        Ok(geoip_reader) => Arc::new(geoip_reader),
-        _ => {
-            println!("Reading GeoIP2 database {} failed", params.geoip);
+    print_step(format!(
+        "Using '{}' as output directory",
+        params.output.output_directory
+    ));
+    print_step(format!("Using '{}' as html output", output.display()));
+    let settings = Arc::new(Settings {
+        config: load_config(&params.config),
+        ..Default::default()
+    });
+    let mut merged = State::new(settings.clone());
+    for path in &params.states {
+        merged.merge(load(path, settings.clone()));
+        print_step(format!("Merged state '{path}'"));
+    }
+    if let Some(path) = &params.state {
+        if let Err(e) = merged.save(Path::new(path)) {
+            println!("Saving state '{path}' failed: {e}");
             process::exit(1);
         }
-    };
-
-    let data = OVERALL_RESULTS_PER_MONTH.read()?;
-
-    for result in &*data {
-        result.ipv4.par_iter().for_each(|(key, value)| {
-            let client_country = match geoip_reader
-                .lookup(std::net::IpAddr::V4(Ipv4Addr::from(*key)))
-                .and_then(|r| r.decode::<maxminddb::geoip2::Country>())
-            {
-                Ok(Some(c)) => c
-                    .country
-                    .iso_code
-                    .map_or("N/A".to_string(), |iso| iso.to_string()),
-                _ => "N/A".to_string(),
-            };
-
-            let mut country_results = COUNTRY_RESULTS.write().unwrap();
-
-            let mut found = false;
-            for country_result in country_results.as_mut_slice() {
-                if country_result.year == result.year
-                    && country_result.country == client_country
-                    && country_result.month == result.month
-                {
-                    country_result.count += *value;
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                country_results.push(ResultCountry {
-                    year: result.year,
-                    month: result.month,
-                    country: client_country,
-                    count: *value,
-                });
-            }
-        });
-        result.ipv6.par_iter().for_each(|(key, value)| {
-            let client_country = match geoip_reader
-                .lookup(std::net::IpAddr::V6(Ipv6Addr::from(*key)))
-                .and_then(|r| r.decode::<maxminddb::geoip2::Country>())
-            {
-                Ok(Some(c)) => c
-                    .country
-                    .iso_code
-                    .map_or("N/A".to_string(), |iso| iso.to_string()),
-                _ => "N/A".to_string(),
-            };
-
-            let mut country_results = COUNTRY_RESULTS.write().unwrap();
-
-            let mut found = false;
-            for country_result in country_results.as_mut_slice() {
-                if country_result.year == result.year
-                    && country_result.country == client_country
-                    && country_result.month == result.month
-                {
-                    country_result.count += *value;
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                country_results.push(ResultCountry {
-                    year: result.year,
-                    month: result.month,
-                    country: client_country,
-                    count: *value,
-                });
-            }
-        });
-    }
-
-    Ok(())
-}
-
-fn create_country_per_year_and_month(
-    params: &Args,
-    json: &mut Json,
-) -> Result<String, Box<dyn std::error::Error>> {
-    fill_country_results(params)?;
-
-    let mut year_months: Vec<String> = Vec::new();
-    let mut countries: Vec<String> = Vec::new();
-
-    let data = COUNTRY_RESULTS.read()?;
-    for result in &*data {
-        year_months.push(format!("{}-{:02}", result.year, result.month));
-        countries.push(result.country.clone());
-    }
-    year_months.sort_unstable();
-    year_months.dedup();
-    countries.sort_unstable();
-    countries.dedup();
-
-    #[derive(Debug)]
-    struct CountryTraceResults {
-        year_months: Vec<String>,
-        count: Vec<i64>,
+        print_step(format!("Saved state to '{path}'"));
     }
-
-    let mut country_results: FxHashMap<String, CountryTraceResults> = FxHashMap::default();
-
-    for country in &countries {
-        for year_month in &year_months {
-            for result in &*data {
-                if format!("{}-{:02}", result.year, result.month) == (*year_month).clone()
-                    && result.country == *country
-                {
-                    let values = match country_results.entry((*country.clone()).to_string()) {
-                        Entry::Occupied(o) => o.into_mut(),
-                        Entry::Vacant(v) => v.insert(CountryTraceResults {
-                            year_months: Vec::new(),
-                            count: Vec::new(),
-                        }),
-                    };
-                    values.year_months.push((*year_month).clone());
-                    values.count.push(result.count);
-                    json.result_country.push(ResultCountry {
-                        year: result.year,
-                        month: result.month,
-                        country: (*country.clone()).to_string(),
-                        count: result.count,
-                    });
-                }
-            }
-        }
-    }
-
-    let mut plot = Plot::new();
-    let layout_country = Layout::new().title("OHPC repository country requests per month");
-    plot.set_layout(layout_country);
-
-    for result in country_results.keys() {
-        let trace_countries = Scatter::new(
-            country_results[result].year_months.clone(),
-            country_results[result].count.clone(),
-        )
-        .name(result);
-        plot.add_trace(trace_countries);
-    }
-
-    Ok(plot.to_inline_html(None))
+    write_report(&merged.aggregator.finish(), params.output, start);
 }
 
-fn create_libdnf_requests_per_year_and_distribution(
-    json: &mut Json,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut ticks: Vec<f64> = Vec::new();
-    let data_libdnf = LIBDNF_RESULTS.read()?;
-    let mut years = Vec::new();
-    let mut distributions: Vec<String> = Vec::new();
-
-    #[derive(Debug)]
-    struct LibdnfTraceResults {
-        years: Vec<i64>,
-        count: Vec<i64>,
-    }
-
-    for result in &*data_libdnf {
-        years.push(result.year);
-        distributions.push(result.name.clone());
-    }
-    years.sort_unstable();
-    years.dedup();
-    distributions.sort_unstable();
-    distributions.dedup();
-
-    for year in years.clone() {
-        for result in &*data_libdnf {
-            if result.year == year {
-                ticks.push((year) as f64);
-                break;
-            }
-        }
-    }
-
-    let mut libdnf_results: FxHashMap<String, LibdnfTraceResults> = FxHashMap::default();
-
-    for distribution in &distributions {
-        for year in &years {
-            for result in &*data_libdnf {
-                if result.year == *year && result.name == *distribution {
-                    let values = match libdnf_results.entry((*distribution.clone()).to_string()) {
-                        Entry::Occupied(o) => o.into_mut(),
-                        Entry::Vacant(v) => v.insert(LibdnfTraceResults {
-                            years: Vec::new(),
-                            count: Vec::new(),
-                        }),
-                    };
-                    values.years.push(*year);
-                    values.count.push(result.count);
-                    json.result_libdnf.push(ResultLIBDNF {
-                        year: *year,
-                        name: result.name.clone(),
-                        count: result.count,
-                    });
-                }
+/// Prints the requests per period of a state file
+fn query(params: QueryArgs) {
+    let settings = Arc::new(Settings {
+        config: load_config(&params.config),
+        ..Default::default()
+    });
+    let report = load(&params.state, settings).aggregator.finish();
+    let mut requests = report.requests_per_period(params.by);
+    if let Some(prefix) = &params.period {
+        requests.retain(|r| r.period.starts_with(prefix.as_str()));
+    }
+    if params.json {
+        match serde_json::to_string_pretty(&requests) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                println!("Serializing results failed: {e}");
+                process::exit(1);
             }
         }
+        return;
     }
-
-    let mut plot_libdnf = Plot::new();
-    let layout_libdnf = Layout::new()
-        .title("OHPC repository libdnf requests per year and distribution")
-        .x_axis(Axis::new().tick_values(ticks));
-    plot_libdnf.set_layout(layout_libdnf);
-
-    for result in libdnf_results.keys() {
-        let trace_libdnf = Scatter::new(
-            libdnf_results[result].years.clone(),
-            libdnf_results[result].count.clone(),
-        )
-        .name(result);
-        plot_libdnf.add_trace(trace_libdnf);
+    println!("period\trequests\tunique\tsize");
+    for r in requests {
+        println!("{}\t{}\t{}\t{}", r.period, r.requests, r.unique, r.size);
     }
-
-    Ok(plot_libdnf.to_inline_html(None))
-}
-
-fn create_plots(params: Args, start: Instant) -> Result<(), Box<dyn std::error::Error>> {
-    let mut years: Vec<i64> = Vec::new();
-    get_years(&mut years)?;
-
-    let mut json = Json {
-        unique_visitors_per_year: Vec::new(),
-        unique_visitors_per_month: Vec::new(),
-        size_per_year: Vec::new(),
-        size_per_month: Vec::new(),
-        result_libdnf: Vec::new(),
-        result_country: Vec::new(),
-    };
-
-    let mut file = File::create(Path::new(&params.output_directory).join(&params.html_output))?;
-    file.write_all(HTML_HEADER.as_bytes())?;
-    file.write_all(create_repository_requests_per_year(&years)?.as_bytes())?;
-    file.write_all(
-        create_unique_repository_requests_per_year(&years, &params, &mut json)?.as_bytes(),
-    )?;
-    file.write_all(create_repository_requests_per_month()?.as_bytes())?;
-    file.write_all(create_unique_repository_requests_per_month(&params, &mut json)?.as_bytes())?;
-    file.write_all(create_data_downloaded_per_year(&years, &params, &mut json)?.as_bytes())?;
-    file.write_all(create_data_downloaded_per_month(&params, &mut json)?.as_bytes())?;
-    file.write_all(create_repository_requests_per_year_and_distribution()?.as_bytes())?;
-    file.write_all(create_libdnf_requests_per_year_and_distribution(&mut json)?.as_bytes())?;
-    file.write_all(create_overall_plot().as_bytes())?;
-    file.write_all(create_type_plot().as_bytes())?;
-    file.write_all(create_country_per_year_and_month(&params, &mut json)?.as_bytes())?;
-    file.write_all("</div>".as_bytes())?;
-    let duration = start.elapsed();
-    let timestamp = chrono::Utc::now().to_rfc2822();
-    file.write_all(
-        format!(
-            "<center>Generated on {timestamp}. Runtime: {duration:?}</center>"
-        )
-        .as_bytes(),
-    )?;
-    file.write_all(HTML_FOOTER.as_bytes())?;
-
-    let mut writer = std::io::BufWriter::new(File::create(
-        Path::new(&params.output_directory).join("stats.json"),
-    )?);
-    serde_json::to_writer(&mut writer, &json)?;
-    writer.flush()?;
-
-    print_step(format!("Finished after {duration:?} at {timestamp}"));
-
-    Ok(())
 }
@@ -0,0 +1,1175 @@
+use rustc_hash::FxHashMap;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::net::{
+    Ipv4Addr,
+    Ipv6Addr,
+};
+use std::path::Path;
+use std::sync::{
+    Arc,
+    RwLock,
+};
+use std::time::Instant;
+
+use plotly::layout::{
+    Axis,
+    BarMode,
+    Layout,
+};
+use plotly::{
+    Bar,
+    HeatMap,
+    ImageFormat,
+    Plot,
+    Scatter,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::aggregator::{
+    ArchitectureRequestsPerPeriod,
+    BotRequestsPerPeriod,
+    Granularity,
+    NetworkRequestsPerPeriod,
+    Period,
+    ReleaseRequestsPerPeriod,
+    Report,
+    RequestsPerPeriod,
+    ResultClient,
+    ResultLIBDNF,
+    ResultPackage,
+    ResultPeriod,
+    UpdateRequestsPerPeriod,
+    UpdateStream,
+    UserAgentRequestsPerPeriod,
+};
+use crate::parser;
+
+/// Number of packages in the rankings of the most downloaded packages
+const TOP_PACKAGES: usize = 20;
+
+/// Number of clients in the monthly rankings of the busiest clients
+const TOP_CLIENTS: usize = 10;
+
+/// Where and how the report is written
+pub struct OutputOptions {
+    /// Directory of the HTML output, the svg files and stats.json
+    pub output_directory: String,
+    /// Name of the HTML output file
+    pub html_output: String,
+    /// Do not write svg output files
+    pub no_svg: bool,
+    /// GeoIP mmdb database
+    pub geoip: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResultCountry {
+    year: i64,
+    month: i64,
+    country: String,
+    count: i64,
+}
+/// Unique clients per release, the releases are named by their key
+#[derive(Serialize)]
+struct UniqueVisitorsPerYear {
+    year: i64,
+    #[serde(flatten)]
+    releases: BTreeMap<String, i64>,
+    overall: i64,
+}
+#[derive(Serialize)]
+struct UniqueVisitorsPerMonth {
+    year_month: String,
+    #[serde(flatten)]
+    releases: BTreeMap<String, i64>,
+    overall: i64,
+}
+#[derive(Serialize)]
+struct SizePerYear {
+    year: i64,
+    size: u64,
+}
+#[derive(Serialize)]
+struct SizePerMonth {
+    year_month: String,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct Json {
+    unique_visitors_per_year: Vec<UniqueVisitorsPerYear>,
+    unique_visitors_per_month: Vec<UniqueVisitorsPerMonth>,
+    size_per_year: Vec<SizePerYear>,
+    size_per_month: Vec<SizePerMonth>,
+    requests_per_day: Vec<RequestsPerPeriod>,
+    requests_per_week: Vec<RequestsPerPeriod>,
+    requests_per_weekday_and_hour: Vec<Vec<usize>>,
+    requests_per_release: Vec<ReleaseRequestsPerPeriod>,
+    requests_per_architecture: Vec<ArchitectureRequestsPerPeriod>,
+    requests_per_update: Vec<UpdateRequestsPerPeriod>,
+    requests_per_user_agent: Vec<UserAgentRequestsPerPeriod>,
+    unaliased_os_names: BTreeMap<String, String>,
+    bot_traffic: Vec<BotRequestsPerPeriod>,
+    probe_clients_per_year: BTreeMap<i64, usize>,
+    top_clients_by_requests: Vec<ResultClient>,
+    top_clients_by_size: Vec<ResultClient>,
+    requests_per_network: Vec<NetworkRequestsPerPeriod>,
+    top_packages: Vec<ResultPackage>,
+    top_package_versions: Vec<ResultPackage>,
+    result_libdnf: Vec<ResultLIBDNF>,
+    result_country: Vec<ResultCountry>,
+    counted_requests: usize,
+    invalid_utf8_lines: usize,
+    outside_date_range_lines: usize,
+    rejected_lines: BTreeMap<&'static str, usize>,
+}
+
+static HTML_HEADER: &str =
+    "<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\" /></head><body>   <div>
+<script src=\"https://cdn.jsdelivr.net/npm/mathjax@3.2.2/es5/tex-svg.js\"></script>
+<script src=\"https://cdn.plot.ly/plotly-2.12.1.min.js\"></script>";
+static HTML_FOOTER: &str = "</body></html>";
+
+/// Returns a value of the results of every release within `period`, 0 for
+/// releases without requests in it
+fn per_release(report: &Report, period: Period, value: impl Fn(&ResultPeriod) -> i64) -> Vec<i64> {
+    (0..report.releases.len())
+        .map(|release| {
+            report
+                .release_result(release, None, period)
+                .map_or(0, &value)
+        })
+        .collect()
+}
+
+/// Assigns the counts of the releases to their keys
+fn release_keys(report: &Report, counts: &[i64]) -> BTreeMap<String, i64> {
+    report
+        .releases
+        .iter()
+        .zip(counts)
+        .map(|(release, count)| (release.key.clone(), *count))
+        .collect()
+}
+
+// The kaleido based image export is deprecated in plotly, but it is the
+// only SVG export available with the currently used plotly version.
+#[allow(deprecated)]
+fn write_svg(plot: &Plot, options: &OutputOptions, name: &str) {
+    plot.write_image(
+        Path::new(&options.output_directory).join(name),
+        ImageFormat::SVG,
+        1600,
+        600,
+        1.0,
+    );
+}
+
+fn create_overall_plot(report: &Report) -> String {
+    let labels = vec!["Accesses"];
+    let mut plot = Plot::new();
+    for (release, requests) in report.releases.iter().zip(&report.release_requests) {
+        plot.add_trace(
+            Bar::new(labels.clone(), vec![*requests]).name(format!("Release {}", release.name)),
+        );
+    }
+    let releases: usize = report.release_requests.iter().sum();
+    plot.add_trace(Bar::new(labels, vec![report.overall.saturating_sub(releases)]).name("Other"));
+    plot.set_layout(
+        Layout::new()
+            .bar_mode(BarMode::Stack)
+            .title("OHPC overall repository accesses"),
+    );
+
+    plot.to_inline_html(None)
+}
+
+fn create_type_plot(report: &Report) -> String {
+    let mut years: Vec<i64> = Vec::new();
+    let mut ticks: Vec<f64> = Vec::new();
+    let data = &report.type_results;
+    for result in data {
+        years.push(result.year);
+    }
+    years.sort_unstable();
+
+    let mut tar: Vec<i64> = Vec::new();
+    let mut rpm: Vec<i64> = Vec::new();
+    let mut repomd_xml: Vec<i64> = Vec::new();
+    for year in &years {
+        for result in data {
+            if result.year == *year {
+                tar.push(result.tar);
+                rpm.push(result.rpm);
+                repomd_xml.push(result.repomd_xml);
+                ticks.push((*year) as f64);
+                break;
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    plot.add_trace(Scatter::new(years.clone(), tar).name("TAR"));
+    plot.add_trace(Scatter::new(years.clone(), rpm).name("RPM"));
+    plot.add_trace(Scatter::new(years.clone(), repomd_xml).name("repomd.xml"));
+
+    plot.set_layout(
+        Layout::new()
+            .title("OHPC file types per year")
+            .x_axis(Axis::new().tick_values(ticks.clone())),
+    );
+
+    plot.to_inline_html(None)
+}
+
+fn get_years(report: &Report, years: &mut Vec<i64>) -> Result<(), Box<dyn std::error::Error>> {
+    let data = &report.overall_results;
+    for result in data {
+        years.push(result.year);
+    }
+    years.sort_unstable();
+
+    Ok(())
+}
+
+fn create_repository_requests_per_year(
+    report: &Report,
+    years: &Vec<i64>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut releases: Vec<Vec<i64>> = vec![Vec::new(); report.releases.len()];
+    let mut overall: Vec<i64> = Vec::new();
+    let mut ticks: Vec<f64> = Vec::new();
+    let data = &report.overall_results;
+
+    for year in years {
+        for result in data {
+            if result.year == *year {
+                let counts = per_release(report, Period::Year(*year), |r| r.overall);
+                for (release, count) in releases.iter_mut().zip(counts) {
+                    release.push(count);
+                }
+                overall.push(result.counts.overall);
+                ticks.push((*year) as f64);
+                break;
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    for (release, counts) in report.releases.iter().zip(releases) {
+        plot.add_trace(Scatter::new(years.clone(), counts).name(format!("OHPC {}", release.name)));
+    }
+    plot.add_trace(Scatter::new(years.clone(), overall).name("Total"));
+    plot.set_layout(
+        Layout::new()
+            .title("OHPC repository requests per year")
+            .x_axis(Axis::new().tick_values(ticks.clone())),
+    );
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn create_unique_repository_requests_per_year(
+    report: &Report,
+    years: &Vec<i64>,
+    options: &OutputOptions,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut unique_releases: Vec<Vec<i64>> = vec![Vec::new(); report.releases.len()];
+    let mut unique_overall: Vec<i64> = Vec::new();
+    let mut ticks: Vec<f64> = Vec::new();
+
+    let data = &report.overall_results;
+    for year in years {
+        for result in data {
+            if result.year == *year {
+                let counts = per_release(report, Period::Year(*year), |r| r.unique_overall);
+                for (release, count) in unique_releases.iter_mut().zip(&counts) {
+                    release.push(*count);
+                }
+                unique_overall.push(result.counts.unique_overall);
+                ticks.push((*year) as f64);
+                json.unique_visitors_per_year.push(UniqueVisitorsPerYear {
+                    year: *year,
+                    releases: release_keys(report, &counts),
+                    overall: result.counts.unique_overall,
+                });
+                break;
+            }
+        }
+    }
+
+    let mut unique_plot = Plot::new();
+    for (release, counts) in report.releases.iter().zip(unique_releases) {
+        unique_plot
+            .add_trace(Scatter::new(years.clone(), counts).name(format!("OHPC {}", release.name)));
+    }
+    unique_plot.add_trace(Scatter::new(years.clone(), unique_overall).name("Total"));
+    let unique_layout = Layout::new()
+        .title("Unique OHPC repository requests per year")
+        .x_axis(Axis::new().tick_values(ticks.clone()));
+    unique_plot.set_layout(unique_layout);
+    if !options.no_svg {
+        write_svg(&unique_plot, options, "unique_visitors_per_year.svg");
+    }
+
+    Ok(unique_plot.to_inline_html(None))
+}
+
+fn create_data_downloaded_per_year(
+    report: &Report,
+    years: &Vec<i64>,
+    options: &OutputOptions,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut ticks: Vec<f64> = Vec::new();
+    let mut size: Vec<u64> = Vec::new();
+    let data = &report.overall_results;
+
+    for year in years {
+        for result in data {
+            if result.year == *year {
+                size.push(result.counts.size);
+                ticks.push((*year) as f64);
+                json.size_per_year.push(SizePerYear {
+                    year: *year,
+                    size: result.counts.size,
+                });
+                break;
+            }
+        }
+    }
+
+    let mut plot_size_per_year = Plot::new();
+    let size_per_year = Scatter::new(years.clone(), size).name("Total");
+    plot_size_per_year.add_trace(size_per_year);
+    let layout_size_per_year = Layout::new()
+        .title("OHPC data downloaded per year")
+        .x_axis(Axis::new().tick_values(ticks.clone()));
+    plot_size_per_year.set_layout(layout_size_per_year);
+
+    if !options.no_svg {
+        write_svg(&plot_size_per_year, options, "size_per_year.svg");
+    }
+
+    Ok(plot_size_per_year.to_inline_html(None))
+}
+
+fn create_repository_requests_per_month(
+    report: &Report,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut releases_per_month: Vec<Vec<i64>> = vec![Vec::new(); report.releases.len()];
+    let mut overall_per_month: Vec<i64> = Vec::new();
+    let mut year_months: Vec<String> = Vec::new();
+
+    let data = &report.overall_results_per_month;
+    for result in data {
+        year_months.push(format!("{}-{:02}", result.year, result.month));
+    }
+    year_months.sort_unstable();
+
+    for year_month in &year_months {
+        for result in data {
+            if format!("{}-{:02}", result.year, result.month) == *year_month {
+                let period = Period::Month(result.year, result.month);
+                let counts = per_release(report, period, |r| r.overall);
+                for (release, count) in releases_per_month.iter_mut().zip(counts) {
+                    release.push(count);
+                }
+                overall_per_month.push(result.counts.overall);
+                break;
+            }
+        }
+    }
+
+    let mut plot_overall_per_month = Plot::new();
+    for (release, counts) in report.releases.iter().zip(releases_per_month) {
+        plot_overall_per_month.add_trace(
+            Scatter::new(year_months.clone(), counts).name(format!("OHPC {}", release.name)),
+        );
+    }
+    plot_overall_per_month
+        .add_trace(Scatter::new(year_months.clone(), overall_per_month).name("Total"));
+    plot_overall_per_month
+        .set_layout(Layout::new().title("OHPC repository requests per month"));
+
+    Ok(plot_overall_per_month.to_inline_html(None))
+}
+
+fn create_requests_per_period(
+    title: &str,
+    data: Vec<RequestsPerPeriod>,
+    json: &mut Vec<RequestsPerPeriod>,
+) -> String {
+    let periods: Vec<String> = data.iter().map(|r| r.period.clone()).collect();
+    let mut plot = Plot::new();
+    plot.add_trace(
+        Scatter::new(periods.clone(), data.iter().map(|r| r.requests).collect()).name("Requests"),
+    );
+    plot.add_trace(
+        Scatter::new(periods, data.iter().map(|r| r.unique).collect()).name("Unique clients"),
+    );
+    plot.set_layout(Layout::new().title(title));
+    *json = data;
+
+    plot.to_inline_html(None)
+}
+
+fn create_repository_requests_per_day(
+    report: &Report,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let requests = report.requests_per_period(Granularity::Day);
+
+    Ok(create_requests_per_period(
+        "OHPC repository requests per day",
+        requests,
+        &mut json.requests_per_day,
+    ))
+}
+
+fn create_repository_requests_per_week(
+    report: &Report,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let requests = report.requests_per_period(Granularity::Week);
+
+    Ok(create_requests_per_period(
+        "OHPC repository requests per ISO week",
+        requests,
+        &mut json.requests_per_week,
+    ))
+}
+
+fn create_requests_per_weekday_and_hour(report: &Report, json: &mut Json) -> String {
+    let weekdays = vec!["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let hours: Vec<usize> = (0..24).collect();
+    let requests = report.requests_per_weekday_and_hour.clone();
+
+    let mut plot = Plot::new();
+    plot.add_trace(HeatMap::new(hours, weekdays, requests.clone()));
+    plot.set_layout(
+        Layout::new()
+            .title("OHPC repository requests per day of the week and hour of the day")
+            .x_axis(Axis::new().title("Hour")),
+    );
+    json.requests_per_weekday_and_hour = requests;
+
+    plot.to_inline_html(None)
+}
+
+fn create_unique_repository_requests_per_month(
+    report: &Report,
+    options: &OutputOptions,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut unique_releases_per_month: Vec<Vec<i64>> = vec![Vec::new(); report.releases.len()];
+    let mut unique_overall_per_month: Vec<i64> = Vec::new();
+    let mut year_months: Vec<String> = Vec::new();
+
+    let data = &report.overall_results_per_month;
+    for result in data {
+        year_months.push(format!("{}-{:02}", result.year, result.month));
+    }
+    year_months.sort_unstable();
+
+    for year_month in &year_months {
+        for result in data {
+            if format!("{}-{:02}", result.year, result.month) == *year_month {
+                let period = Period::Month(result.year, result.month);
+                let counts = per_release(report, period, |r| r.unique_overall);
+                for (release, count) in unique_releases_per_month.iter_mut().zip(&counts) {
+                    release.push(*count);
+                }
+                unique_overall_per_month.push(result.counts.unique_overall);
+                json.unique_visitors_per_month.push(UniqueVisitorsPerMonth {
+                    year_month: year_month.clone(),
+                    releases: release_keys(report, &counts),
+                    overall: result.counts.unique_overall,
+                });
+                break;
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    for (release, counts) in report.releases.iter().zip(unique_releases_per_month) {
+        plot.add_trace(
+            Scatter::new(year_months.clone(), counts).name(format!("OHPC {}", release.name)),
+        );
+    }
+    plot.add_trace(Scatter::new(year_months.clone(), unique_overall_per_month).name("Total"));
+    plot.set_layout(Layout::new().title("Unique OHPC repository requests per month"));
+
+    if !options.no_svg {
+        write_svg(&plot, options, "unique_visitors_per_month.svg");
+    }
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn create_repository_requests_per_year_and_distribution(
+    report: &Report,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut plot = Plot::new();
+    let mut ticks: Vec<f64> = Vec::new();
+
+    for (index, release) in report.releases.iter().enumerate() {
+        for (distribution_index, distribution) in release.distributions.iter().enumerate() {
+            // Sorted by release, distribution and period
+            let (years, counts): (Vec<i64>, Vec<i64>) = report
+                .release_results
+                .iter()
+                .filter(|(key, _)| {
+                    key.release == index && key.distribution == Some(distribution_index)
+                })
+                .filter_map(|(key, r)| match key.period {
+                    Period::Year(year) => Some((year, r.overall)),
+                    Period::Month(..) => None,
+                })
+                .unzip();
+            for year in &years {
+                if !ticks.contains(&(*year as f64)) {
+                    ticks.push(*year as f64);
+                }
+            }
+            plot.add_trace(
+                Scatter::new(years, counts)
+                    .name(format!("OHPC {} {}", distribution.name, release.name)),
+            );
+        }
+    }
+    plot.set_layout(
+        Layout::new()
+            .title("OHPC repository requests per year and distribution")
+            .x_axis(Axis::new().tick_values(ticks)),
+    );
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn create_unique_repository_requests_per_month_and_architecture(
+    report: &Report,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let year_months: Vec<(i64, i64)> = report
+        .overall_results_per_month
+        .iter()
+        .map(|r| (r.year, r.month))
+        .collect();
+    let labels: Vec<String> = year_months
+        .iter()
+        .map(|(year, month)| Period::Month(*year, *month).to_string())
+        .collect();
+
+    let mut plot = Plot::new();
+    for (index, architecture) in report.architectures.iter().enumerate() {
+        let unique: Vec<i64> = year_months
+            .iter()
+            .map(|(year, month)| {
+                report
+                    .architecture_result(index, None, Period::Month(*year, *month))
+                    .map_or(0, |r| r.unique_overall)
+            })
+            .collect();
+        plot.add_trace(Scatter::new(labels.clone(), unique).name(architecture));
+    }
+    plot.set_layout(
+        Layout::new().title("Unique OHPC repository requests per month and architecture"),
+    );
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn create_unique_repository_requests_per_month_and_point_release(
+    report: &Report,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let year_months: Vec<(i64, i64)> = report
+        .overall_results_per_month
+        .iter()
+        .map(|r| (r.year, r.month))
+        .collect();
+    let labels: Vec<String> = year_months
+        .iter()
+        .map(|(year, month)| Period::Month(*year, *month).to_string())
+        .collect();
+
+    let mut plot = Plot::new();
+    for (index, point_release) in report.point_releases() {
+        let unique: Vec<i64> = year_months
+            .iter()
+            .map(|(year, month)| {
+                report
+                    .update_result(
+                        index,
+                        UpdateStream::PointRelease(point_release.clone()),
+                        Period::Month(*year, *month),
+                    )
+                    .map_or(0, |r| r.unique_overall)
+            })
+            .collect();
+        plot.add_trace(Scatter::new(labels.clone(), unique).name(format!(
+            "OHPC {} {point_release}",
+            report.releases[index].name
+        )));
+    }
+    plot.set_layout(
+        Layout::new().title("Unique OHPC repository requests per month and point release"),
+    );
+
+    Ok(plot.to_inline_html(None))
+}
+
+/// Returns the most downloaded packages of every year, for all downloads
+/// and per release
+fn top_packages_per_year_and_release(
+    report: &Report,
+    years: &[i64],
+    versions: bool,
+) -> Vec<ResultPackage> {
+    let releases: Vec<Option<usize>> = std::iter::once(None)
+        .chain((0..report.releases.len()).map(Some))
+        .collect();
+    years
+        .iter()
+        .flat_map(|year| {
+            releases.iter().flat_map(move |release| {
+                report.top_packages(Some(*year), *release, versions, TOP_PACKAGES)
+            })
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn create_top_packages(report: &Report, years: &[i64]) -> String {
+    let names: Vec<String> = report
+        .top_packages(None, None, false, TOP_PACKAGES)
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+
+    let mut plot = Plot::new();
+    for year in years {
+        let counts: Vec<i64> = names
+            .iter()
+            .map(|name| {
+                report
+                    .package_results
+                    .iter()
+                    .filter(|p| p.year == *year && p.release.is_none() && p.name == *name)
+                    .map(|p| p.count)
+                    .sum()
+            })
+            .collect();
+        if counts.iter().any(|c| *c > 0) {
+            plot.add_trace(Bar::new(names.clone(), counts).name(year.to_string()));
+        }
+    }
+    plot.set_layout(
+        Layout::new()
+            .bar_mode(BarMode::Stack)
+            .title("Most downloaded OHPC packages"),
+    );
+
+    let mut table = String::from(
+        "<table><caption>Most downloaded OHPC package versions per release</caption>\
+         <tr><th>Release</th><th>Package</th><th>Version</th><th>Downloads</th></tr>",
+    );
+    for (index, release) in report.releases.iter().enumerate() {
+        for package in report.top_packages(None, Some(index), true, TOP_PACKAGES) {
+            table.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&release.name),
+                escape_html(&package.name),
+                escape_html(package.version.as_deref().unwrap_or_default()),
+                package.count
+            ));
+        }
+    }
+    table.push_str("</table>");
+
+    plot.to_inline_html(None) + &table
+}
+
+/// Returns the busiest clients of every month
+fn top_clients_per_month(report: &Report, by_size: bool) -> Vec<ResultClient> {
+    report
+        .overall_results_per_month
+        .iter()
+        .flat_map(|r| report.top_clients(r.year, r.month, by_size, TOP_CLIENTS))
+        .collect()
+}
+
+fn create_top_clients(report: &Report) -> String {
+    let (year, month) = match report.overall_results_per_month.last() {
+        Some(r) => (r.year, r.month),
+        None => return String::new(),
+    };
+    let mut table = format!(
+        "<table><caption>Clients with the most requests in {}</caption>\
+         <tr><th>Client</th><th>Network</th><th>Requests</th><th>Share</th>\
+         <th>Bytes</th><th>Share</th></tr>",
+        Period::Month(year, month)
+    );
+    for client in report.top_clients(year, month, false, TOP_CLIENTS) {
+        table.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1} %</td><td>{}</td><td>{:.1} %</td></tr>",
+            escape_html(&client.ip),
+            escape_html(client.network.as_deref().unwrap_or_default()),
+            client.requests,
+            client.requests_share * 100.0,
+            client.size,
+            client.size_share * 100.0
+        ));
+    }
+    table.push_str("</table>");
+    table
+}
+
+fn create_data_downloaded_per_month(
+    report: &Report,
+    options: &OutputOptions,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut size_per_month: Vec<u64> = Vec::new();
+    let mut year_months: Vec<String> = Vec::new();
+
+    let data = &report.overall_results_per_month;
+    for result in data {
+        year_months.push(format!("{}-{:02}", result.year, result.month));
+    }
+    year_months.sort_unstable();
+
+    for year_month in &year_months {
+        for result in data {
+            if format!("{}-{:02}", result.year, result.month) == *year_month {
+                size_per_month.push(result.counts.size);
+                json.size_per_month.push(SizePerMonth {
+                    year_month: year_month.clone(),
+                    size: result.counts.size,
+                });
+                break;
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    plot.add_trace(Scatter::new(year_months.clone(), size_per_month).name("Total"));
+    plot.set_layout(Layout::new().title("OHPC data downloaded per month"));
+
+    if !options.no_svg {
+        write_svg(&plot, options, "size_per_month.svg");
+    }
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn fill_country_results(
+    report: &Report,
+    options: &OutputOptions,
+) -> Result<Vec<ResultCountry>, Box<dyn std::error::Error>> {
+    let geoip_reader = match maxminddb::Reader::open_readfile(&options.geoip) {
+        Ok(geoip_reader) => Arc::new(geoip_reader),
+        _ => return Err(format!("Reading GeoIP2 database {} failed", options.geoip).into()),
+    };
+
+    let data = &report.overall_results_per_month;
+    let country_results: RwLock<Vec<ResultCountry>> = RwLock::new(Vec::new());
+
+    for result in data {
+        result.counts.ipv4.par_iter().for_each(|(key, value)| {
+            let client_country = match geoip_reader
+                .lookup(std::net::IpAddr::V4(Ipv4Addr::from(*key)))
+                .and_then(|r| r.decode::<maxminddb::geoip2::Country>())
+            {
+                Ok(Some(c)) => c
+                    .country
+                    .iso_code
+                    .map_or("N/A".to_string(), |iso| iso.to_string()),
+                _ => "N/A".to_string(),
+            };
+
+            let mut country_results = country_results.write().unwrap();
+
+            let mut found = false;
+            for country_result in country_results.as_mut_slice() {
+                if country_result.year == result.year
+                    && country_result.country == client_country
+                    && country_result.month == result.month
+                {
+                    country_result.count += value.requests;
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                country_results.push(ResultCountry {
+                    year: result.year,
+                    month: result.month,
+                    country: client_country,
+                    count: value.requests,
+                });
+            }
+        });
+        result.counts.ipv6.par_iter().for_each(|(key, value)| {
+            let client_country = match geoip_reader
+                .lookup(std::net::IpAddr::V6(Ipv6Addr::from(*key)))
+                .and_then(|r| r.decode::<maxminddb::geoip2::Country>())
+            {
+                Ok(Some(c)) => c
+                    .country
+                    .iso_code
+                    .map_or("N/A".to_string(), |iso| iso.to_string()),
+                _ => "N/A".to_string(),
+            };
+
+            let mut country_results = country_results.write().unwrap();
+
+            let mut found = false;
+            for country_result in country_results.as_mut_slice() {
+                if country_result.year == result.year
+                    && country_result.country == client_country
+                    && country_result.month == result.month
+                {
+                    country_result.count += value.requests;
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                country_results.push(ResultCountry {
+                    year: result.year,
+                    month: result.month,
+                    country: client_country,
+                    count: value.requests,
+                });
+            }
+        });
+    }
+
+    Ok(country_results.into_inner().unwrap())
+}
+
+fn create_country_per_year_and_month(
+    report: &Report,
+    options: &OutputOptions,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let data = &fill_country_results(report, options)?;
+
+    let mut year_months: Vec<String> = Vec::new();
+    let mut countries: Vec<String> = Vec::new();
+
+    for result in data {
+        year_months.push(format!("{}-{:02}", result.year, result.month));
+        countries.push(result.country.clone());
+    }
+    year_months.sort_unstable();
+    year_months.dedup();
+    countries.sort_unstable();
+    countries.dedup();
+
+    #[derive(Debug)]
+    struct CountryTraceResults {
+        year_months: Vec<String>,
+        count: Vec<i64>,
+    }
+
+    let mut country_results: FxHashMap<String, CountryTraceResults> = FxHashMap::default();
+
+    for country in &countries {
+        for year_month in &year_months {
+            for result in data {
+                if format!("{}-{:02}", result.year, result.month) == (*year_month).clone()
+                    && result.country == *country
+                {
+                    let values = match country_results.entry((*country.clone()).to_string()) {
+                        Entry::Occupied(o) => o.into_mut(),
+                        Entry::Vacant(v) => v.insert(CountryTraceResults {
+                            year_months: Vec::new(),
+                            count: Vec::new(),
+                        }),
+                    };
+                    values.year_months.push((*year_month).clone());
+                    values.count.push(result.count);
+                    json.result_country.push(ResultCountry {
+                        year: result.year,
+                        month: result.month,
+                        country: (*country.clone()).to_string(),
+                        count: result.count,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    let layout_country = Layout::new().title("OHPC repository country requests per month");
+    plot.set_layout(layout_country);
+
+    for result in country_results.keys() {
+        let trace_countries = Scatter::new(
+            country_results[result].year_months.clone(),
+            country_results[result].count.clone(),
+        )
+        .name(result);
+        plot.add_trace(trace_countries);
+    }
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn create_libdnf_requests_per_year_and_distribution(
+    report: &Report,
+    json: &mut Json,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut ticks: Vec<f64> = Vec::new();
+    let data_libdnf = &report.libdnf_results;
+    let mut years = Vec::new();
+    let mut distributions: Vec<String> = Vec::new();
+
+    #[derive(Debug)]
+    struct LibdnfTraceResults {
+        years: Vec<i64>,
+        count: Vec<i64>,
+    }
+
+    for result in data_libdnf {
+        years.push(result.year);
+        distributions.push(result.name.clone());
+    }
+    years.sort_unstable();
+    years.dedup();
+    distributions.sort_unstable();
+    distributions.dedup();
+
+    for year in years.clone() {
+        for result in data_libdnf {
+            if result.year == year {
+                ticks.push((year) as f64);
+                break;
+            }
+        }
+    }
+
+    let mut libdnf_results: FxHashMap<String, LibdnfTraceResults> = FxHashMap::default();
+
+    for distribution in &distributions {
+        for year in &years {
+            for result in data_libdnf {
+                if result.year == *year && result.name == *distribution {
+                    let values = match libdnf_results.entry((*distribution.clone()).to_string()) {
+                        Entry::Occupied(o) => o.into_mut(),
+                        Entry::Vacant(v) => v.insert(LibdnfTraceResults {
+                            years: Vec::new(),
+                            count: Vec::new(),
+                        }),
+                    };
+                    values.years.push(*year);
+                    values.count.push(result.count);
+                    json.result_libdnf.push(ResultLIBDNF {
+                        year: *year,
+                        name: result.name.clone(),
+                        count: result.count,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut plot_libdnf = Plot::new();
+    let layout_libdnf = Layout::new()
+        .title("OHPC repository libdnf requests per year and distribution")
+        .x_axis(Axis::new().tick_values(ticks));
+    plot_libdnf.set_layout(layout_libdnf);
+
+    for result in libdnf_results.keys() {
+        let trace_libdnf = Scatter::new(
+            libdnf_results[result].years.clone(),
+            libdnf_results[result].count.clone(),
+        )
+        .name(result);
+        plot_libdnf.add_trace(trace_libdnf);
+    }
+
+    Ok(plot_libdnf.to_inline_html(None))
+}
+
+fn create_requests_per_year_and_client(
+    report: &Report,
+    years: &[i64],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut requests: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
+    for (key, count) in &report.user_agent_results {
+        if key.release.is_some() {
+            continue;
+        }
+        if let Period::Year(year) = key.period {
+            if let Some(index) = years.iter().position(|y| *y == year) {
+                requests
+                    .entry(key.client)
+                    .or_insert_with(|| vec![0; years.len()])[index] += count;
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    for (client, counts) in requests {
+        plot.add_trace(Bar::new(years.to_vec(), counts).name(client));
+    }
+    plot.set_layout(
+        Layout::new()
+            .title("OHPC repository requests per year and client")
+            .bar_mode(BarMode::Stack)
+            .x_axis(Axis::new().tick_values(years.iter().map(|y| *y as f64).collect())),
+    );
+
+    Ok(plot.to_inline_html(None))
+}
+
+fn create_requests_per_month_release_and_os_version(
+    report: &Report,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let year_months: Vec<(i64, i64)> = report
+        .overall_results_per_month
+        .iter()
+        .map(|r| (r.year, r.month))
+        .collect();
+    let labels: Vec<String> = year_months
+        .iter()
+        .map(|(year, month)| Period::Month(*year, *month).to_string())
+        .collect();
+
+    // Requests per release, operating system and major version
+    let mut requests: BTreeMap<(usize, &str, &str), Vec<i64>> = BTreeMap::new();
+    for (key, count) in &report.user_agent_results {
+        let (release, os, os_version) = match (key.release, &key.os, &key.os_version) {
+            (Some(release), Some(os), Some(os_version)) => (release, os, os_version),
+            _ => continue,
+        };
+        if let Period::Month(year, month) = key.period {
+            if let Some(index) = year_months.iter().position(|m| *m == (year, month)) {
+                requests
+                    .entry((release, os, parser::major_version(os_version)))
+                    .or_insert_with(|| vec![0; year_months.len()])[index] += count;
+            }
+        }
+    }
+
+    let mut plot = Plot::new();
+    for ((release, os, major), counts) in requests {
+        plot.add_trace(Scatter::new(labels.clone(), counts).name(format!(
+            "OHPC {} {os} {major}",
+            report.releases[release].name
+        )));
+    }
+    plot.set_layout(
+        Layout::new().title("OHPC repository requests per month, release and OS version"),
+    );
+
+    Ok(plot.to_inline_html(None))
+}
+
+/// Writes the HTML report with all plots, the svg files and stats.json
+pub fn write_report(
+    report: &Report,
+    options: &OutputOptions,
+    start: Instant,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut years: Vec<i64> = Vec::new();
+    get_years(report, &mut years)?;
+
+    let mut json = Json {
+        unique_visitors_per_year: Vec::new(),
+        unique_visitors_per_month: Vec::new(),
+        size_per_year: Vec::new(),
+        size_per_month: Vec::new(),
+        requests_per_day: Vec::new(),
+        requests_per_week: Vec::new(),
+        requests_per_weekday_and_hour: Vec::new(),
+        requests_per_release: report.requests_per_release(),
+        requests_per_architecture: report.requests_per_architecture(),
+        requests_per_update: report.requests_per_update(),
+        requests_per_user_agent: report.requests_per_user_agent(),
+        unaliased_os_names: report
+            .unaliased_os_names
+            .iter()
+            .map(|(name, first_seen)| (name.clone(), first_seen.to_string()))
+            .collect(),
+        bot_traffic: report.bot_traffic(),
+        probe_clients_per_year: report.probe_clients.clone(),
+        top_clients_by_requests: top_clients_per_month(report, false),
+        top_clients_by_size: top_clients_per_month(report, true),
+        requests_per_network: report.requests_per_network(),
+        top_packages: top_packages_per_year_and_release(report, &years, false),
+        top_package_versions: top_packages_per_year_and_release(report, &years, true),
+        result_libdnf: Vec::new(),
+        result_country: Vec::new(),
+        counted_requests: report.counted_requests,
+        invalid_utf8_lines: report.invalid_utf8_lines,
+        outside_date_range_lines: report.outside_date_range_lines,
+        rejected_lines: report.rejected_lines.clone(),
+    };
+
+    let mut file = File::create(Path::new(&options.output_directory).join(&options.html_output))?;
+    file.write_all(HTML_HEADER.as_bytes())?;
+    file.write_all(create_repository_requests_per_year(report, &years)?.as_bytes())?;
+    file.write_all(
+        create_unique_repository_requests_per_year(report, &years, options, &mut json)?.as_bytes(),
+    )?;
+    file.write_all(create_repository_requests_per_month(report)?.as_bytes())?;
+    file.write_all(create_repository_requests_per_week(report, &mut json)?.as_bytes())?;
+    file.write_all(create_repository_requests_per_day(report, &mut json)?.as_bytes())?;
+    file.write_all(create_requests_per_weekday_and_hour(report, &mut json).as_bytes())?;
+    file.write_all(
+        create_unique_repository_requests_per_month(report, options, &mut json)?.as_bytes(),
+    )?;
+    file.write_all(
+        create_data_downloaded_per_year(report, &years, options, &mut json)?.as_bytes(),
+    )?;
+    file.write_all(create_data_downloaded_per_month(report, options, &mut json)?.as_bytes())?;
+    file.write_all(create_repository_requests_per_year_and_distribution(report)?.as_bytes())?;
+    file.write_all(
+        create_unique_repository_requests_per_month_and_architecture(report)?.as_bytes(),
+    )?;
+    file.write_all(
+        create_unique_repository_requests_per_month_and_point_release(report)?.as_bytes(),
+    )?;
+    file.write_all(
+        create_libdnf_requests_per_year_and_distribution(report, &mut json)?.as_bytes(),
+    )?;
+    file.write_all(create_requests_per_year_and_client(report, &years)?.as_bytes())?;
+    file.write_all(create_requests_per_month_release_and_os_version(report)?.as_bytes())?;
+    file.write_all(create_overall_plot(report).as_bytes())?;
+    file.write_all(create_type_plot(report).as_bytes())?;
+    file.write_all(create_top_packages(report, &years).as_bytes())?;
+    file.write_all(create_top_clients(report).as_bytes())?;
+    file.write_all(create_country_per_year_and_month(report, options, &mut json)?.as_bytes())?;
+    file.write_all("</div>".as_bytes())?;
+    let duration = start.elapsed();
+    let timestamp = chrono::Utc::now().to_rfc2822();
+    file.write_all(
+        format!(
+            "<center>Generated on {timestamp}. Runtime: {duration:?}</center>"
+        )
+        .as_bytes(),
+    )?;
+    file.write_all(HTML_FOOTER.as_bytes())?;
+
+    let mut writer = std::io::BufWriter::new(File::create(
+        Path::new(&options.output_directory).join("stats.json"),
+    )?);
+    serde_json::to_writer(&mut writer, &json)?;
+    writer.flush()?;
+
+    Ok(())
+}
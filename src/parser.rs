@@ -0,0 +1,792 @@
+use aho_corasick::AhoCorasick;
+use chrono::{
+    DateTime,
+    FixedOffset,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::sync::LazyLock;
+
+/// One request from an Apache Common or Combined log line.
+///
+/// All string fields borrow from the parsed line. Fields which are logged
+/// as `-` are returned as empty strings (`ident`, `user`) or `None`.
+#[derive(Debug, Default, PartialEq)]
+pub struct LogRecord<'a> {
+    pub ip: &'a str,
+    pub ident: &'a str,
+    pub user: &'a str,
+    /// Timestamp without the surrounding brackets, e.g.
+    /// `27/Apr/2023:00:11:57 +0000`
+    pub timestamp: &'a str,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub protocol: &'a str,
+    pub status: Option<u16>,
+    pub bytes: u64,
+    pub referer: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+}
+
+/// nginx's predefined `combined` log format
+static NGINX_COMBINED: &str = "$remote_addr - $remote_user [$time_local] \"$request\" $status \
+                               $body_bytes_sent \"$http_referer\" \"$http_user_agent\"";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Ip,
+    Ident,
+    User,
+    Timestamp,
+    Request,
+    Method,
+    Path,
+    Protocol,
+    Status,
+    Bytes,
+    Referer,
+    UserAgent,
+    Ignore,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Element {
+    Literal(String),
+    Field(Field),
+}
+
+/// Line format of the access logs.
+///
+/// `Combined` uses the generic tokenizer which understands the Apache
+/// Common and Combined formats. `Custom` is compiled from an Apache
+/// `LogFormat` directive or an nginx `log_format` string.
+#[derive(Debug, Default)]
+pub enum LogFormat {
+    #[default]
+    Combined,
+    Custom(Vec<Element>),
+}
+
+/// Splits a log line into its fields. Fields are separated by one or more
+/// spaces, quoted fields may contain spaces and backslash escaped quotes and
+/// bracketed fields may contain spaces.
+fn tokenize(line: &str) -> Vec<&str> {
+    let bytes = line.as_bytes();
+    let mut fields = Vec::with_capacity(12);
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            b'"' => {
+                let start = i + 1;
+                i = start;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                fields.push(&line[start..i.min(bytes.len())]);
+                i += 1;
+            }
+            b'[' => {
+                let start = i + 1;
+                i = start;
+                while i < bytes.len() && bytes[i] != b']' {
+                    i += 1;
+                }
+                fields.push(&line[start..i.min(bytes.len())]);
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < bytes.len() && bytes[i] != b' ' && bytes[i] != b'\t' {
+                    i += 1;
+                }
+                fields.push(&line[start..i]);
+            }
+        }
+    }
+    fields
+}
+
+fn dash_to_empty(field: &str) -> &str {
+    match field {
+        "-" => "",
+        f => f,
+    }
+}
+
+fn dash_to_none<'a>(field: Option<&&'a str>) -> Option<&'a str> {
+    match field {
+        None | Some(&"-") => None,
+        Some(f) => Some(f),
+    }
+}
+
+/// Parses the request line (`GET /path HTTP/1.1`) into method, path and
+/// protocol. The path may contain spaces if the client did not encode them.
+fn parse_request(request: &str) -> (&str, &str, &str) {
+    let request = request.trim();
+    let (method, rest) = match request.split_once(' ') {
+        Some((method, rest)) => (method, rest.trim_start()),
+        None => return ("", request, ""),
+    };
+    match rest.rsplit_once(' ') {
+        Some((path, protocol)) if protocol.starts_with("HTTP/") => {
+            (method, path.trim_end(), protocol)
+        }
+        _ => (method, rest, ""),
+    }
+}
+
+fn header_field(name: &str) -> Field {
+    match name.to_ascii_lowercase().replace('_', "-").as_str() {
+        "referer" => Field::Referer,
+        "user-agent" => Field::UserAgent,
+        _ => Field::Ignore,
+    }
+}
+
+fn apache_field(directive: char, name: Option<&str>) -> Result<Field, String> {
+    Ok(match (directive, name) {
+        ('h' | 'a', _) => Field::Ip,
+        ('l', _) => Field::Ident,
+        ('u', _) => Field::User,
+        ('t', None) => Field::Timestamp,
+        ('r', _) => Field::Request,
+        ('m', _) => Field::Method,
+        ('U', _) => Field::Path,
+        ('H', _) => Field::Protocol,
+        ('s', _) => Field::Status,
+        ('b' | 'B' | 'O', _) => Field::Bytes,
+        ('i', Some(name)) => header_field(name),
+        ('A' | 'C' | 'D' | 'e' | 'f' | 'I' | 'k' | 'L' | 'n' | 'o' | 'p' | 'P' | 'q' | 'R', _)
+        | ('S' | 'T' | 'v' | 'V' | 'X' | 'M', _) => Field::Ignore,
+        _ => {
+            return Err(format!(
+                "Unsupported LogFormat directive '%{}{directive}'",
+                name.map(|n| format!("{{{n}}}")).unwrap_or_default()
+            ))
+        }
+    })
+}
+
+fn nginx_field(variable: &str) -> Field {
+    match variable {
+        "remote_addr" | "realip_remote_addr" => Field::Ip,
+        "remote_user" => Field::User,
+        "time_local" => Field::Timestamp,
+        "request" => Field::Request,
+        "request_method" => Field::Method,
+        "request_uri" | "uri" => Field::Path,
+        "server_protocol" => Field::Protocol,
+        "status" => Field::Status,
+        "body_bytes_sent" | "bytes_sent" => Field::Bytes,
+        v => match v.strip_prefix("http_") {
+            Some(header) => header_field(header),
+            None => Field::Ignore,
+        },
+    }
+}
+
+fn push_field(literal: &mut String, elements: &mut Vec<Element>, field: Field) {
+    if !literal.is_empty() {
+        elements.push(Element::Literal(std::mem::take(literal)));
+    }
+    elements.push(Element::Field(field));
+}
+
+impl LogFormat {
+    /// Creates the line format from a preset name (`combined`, `common`,
+    /// `nginx-combined`) or from an Apache `LogFormat`/nginx `log_format`
+    /// string.
+    pub fn new(format: &str) -> Result<LogFormat, String> {
+        match format {
+            "combined" | "common" => Ok(LogFormat::Combined),
+            "nginx-combined" => LogFormat::compile(NGINX_COMBINED),
+            f => LogFormat::compile(f),
+        }
+    }
+
+    fn compile(format: &str) -> Result<LogFormat, String> {
+        let mut elements: Vec<Element> = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some(e) => literal.push(e),
+                    None => literal.push(c),
+                },
+                '%' => {
+                    if chars.peek() == Some(&'%') {
+                        chars.next();
+                        literal.push('%');
+                        continue;
+                    }
+                    // Skip status code conditions and the '<' and '>' modifiers
+                    while let Some(m) = chars.peek() {
+                        if m.is_ascii_digit() || matches!(m, '<' | '>' | '!' | ',') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    let mut name = None;
+                    if chars.peek() == Some(&'{') {
+                        chars.next();
+                        name = Some(chars.by_ref().take_while(|n| *n != '}').collect::<String>());
+                    }
+                    let directive = match chars.next() {
+                        Some(d) => d,
+                        None => {
+                            return Err(format!("Incomplete LogFormat directive in '{format}'"))
+                        }
+                    };
+                    let field = apache_field(directive, name.as_deref())?;
+                    push_field(&mut literal, &mut elements, field);
+                }
+                '$' => {
+                    let mut variable = String::new();
+                    if chars.peek() == Some(&'{') {
+                        chars.next();
+                        variable = chars.by_ref().take_while(|n| *n != '}').collect();
+                    } else {
+                        while let Some(v) = chars.peek() {
+                            if v.is_ascii_alphanumeric() || *v == '_' {
+                                variable.push(*v);
+                                chars.next();
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                    if variable.is_empty() {
+                        literal.push(c);
+                        continue;
+                    }
+                    push_field(&mut literal, &mut elements, nginx_field(&variable));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            elements.push(Element::Literal(literal));
+        }
+
+        for required in [Field::Ip, Field::Timestamp] {
+            if !elements.contains(&Element::Field(required)) {
+                return Err(format!(
+                    "Log format '{format}' has no {} field",
+                    match required {
+                        Field::Ip => "client address",
+                        _ => "timestamp",
+                    }
+                ));
+            }
+        }
+        if !elements.contains(&Element::Field(Field::Request))
+            && !elements.contains(&Element::Field(Field::Path))
+        {
+            return Err(format!("Log format '{format}' has no request field"));
+        }
+
+        Ok(LogFormat::Custom(elements))
+    }
+
+    /// Parses one access log line in this format
+    pub fn parse<'a>(&self, line: &'a str) -> Option<LogRecord<'a>> {
+        let elements = match self {
+            LogFormat::Combined => return parse_line(line),
+            LogFormat::Custom(elements) => elements,
+        };
+        let line = line.trim_end();
+        let bytes = line.as_bytes();
+        let mut record = LogRecord::default();
+        let mut pos = 0;
+
+        for (i, element) in elements.iter().enumerate() {
+            let field = match element {
+                Element::Literal(literal) => {
+                    pos = match_literal(bytes, pos, literal)?;
+                    continue;
+                }
+                Element::Field(field) => *field,
+            };
+            let quoted =
+                i > 0 && matches!(&elements[i - 1], Element::Literal(l) if l.ends_with('"'));
+            let end = if quoted {
+                let mut end = pos;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    if bytes[end] == b'\\' {
+                        end += 1;
+                    }
+                    end += 1;
+                }
+                end.min(bytes.len())
+            } else if field == Field::Timestamp && bytes.get(pos) == Some(&b'[') {
+                pos + line[pos..].find(']')? + 1
+            } else {
+                match elements.get(i + 1) {
+                    Some(Element::Literal(next)) => {
+                        let first = next.chars().next()?;
+                        pos + line[pos..].find(first)?
+                    }
+                    Some(Element::Field(_)) => {
+                        pos + line[pos..].find([' ', '\t']).unwrap_or(line.len() - pos)
+                    }
+                    None => line.len(),
+                }
+            };
+            let value = &line[pos..end];
+            pos = end;
+
+            match field {
+                Field::Ip => record.ip = value,
+                Field::Ident => record.ident = dash_to_empty(value),
+                Field::User => record.user = dash_to_empty(value),
+                Field::Timestamp => {
+                    record.timestamp = value.trim_start_matches('[').trim_end_matches(']')
+                }
+                Field::Request => {
+                    (record.method, record.path, record.protocol) = parse_request(value)
+                }
+                Field::Method => record.method = value,
+                Field::Path => record.path = value,
+                Field::Protocol => record.protocol = value,
+                Field::Status => record.status = value.parse::<u16>().ok(),
+                Field::Bytes => record.bytes = value.parse::<u64>().unwrap_or_default(),
+                Field::Referer => record.referer = dash_to_none(Some(&value)),
+                Field::UserAgent => record.user_agent = dash_to_none(Some(&value)),
+                Field::Ignore => (),
+            }
+        }
+
+        Some(record)
+    }
+}
+
+/// Matches a literal part of a log format at `pos` and returns the position
+/// after it. A space in the format matches one or more spaces in the line.
+fn match_literal(line: &[u8], mut pos: usize, literal: &str) -> Option<usize> {
+    for c in literal.bytes() {
+        if c == b' ' {
+            if line.get(pos) != Some(&b' ') {
+                return None;
+            }
+            while line.get(pos) == Some(&b' ') {
+                pos += 1;
+            }
+        } else if line.get(pos) == Some(&c) {
+            pos += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(pos)
+}
+
+/// Parses an access log timestamp (`27/Apr/2023:00:11:57 +0000`) including
+/// its UTC offset.
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_str(timestamp, "%d/%b/%Y:%H:%M:%S %z").ok()
+}
+
+/// Parses one line in the Apache Common or Combined log format. Returns
+/// `None` if the line does not contain at least the host, ident, user,
+/// timestamp and request fields.
+pub fn parse_line(line: &str) -> Option<LogRecord<'_>> {
+    let fields = tokenize(line);
+    if fields.len() < 5 {
+        return None;
+    }
+    let (method, path, protocol) = parse_request(fields[4]);
+
+    Some(LogRecord {
+        ip: fields[0],
+        ident: dash_to_empty(fields[1]),
+        user: dash_to_empty(fields[2]),
+        timestamp: fields[3],
+        method,
+        path,
+        protocol,
+        status: fields.get(5).and_then(|s| s.parse::<u16>().ok()),
+        bytes: fields
+            .get(6)
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or_default(),
+        referer: dash_to_none(fields.get(7)),
+        user_agent: dash_to_none(fields.get(8)),
+    })
+}
+
+/// Name, version, release and architecture of an RPM package file
+#[derive(Debug, PartialEq)]
+pub struct RpmFile<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub release: &'a str,
+    pub arch: &'a str,
+}
+
+/// Parses the file name of a request path like
+/// `/OpenHPC/3/EL_9/x86_64/pdsh-ohpc-2.34-300.ohpc.1.5.x86_64.rpm` into
+/// `pdsh-ohpc`, `2.34`, `300.ohpc.1.5` and `x86_64`
+pub fn parse_rpm(path: &str) -> Option<RpmFile<'_>> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    let (rest, arch) = file_name.strip_suffix(".rpm")?.rsplit_once('.')?;
+    let (rest, release) = rest.rsplit_once('-')?;
+    let (name, version) = rest.rsplit_once('-')?;
+    if name.is_empty() || version.is_empty() || release.is_empty() || arch.is_empty() {
+        return None;
+    }
+    Some(RpmFile {
+        name,
+        version,
+        release,
+        arch,
+    })
+}
+
+/// Update repository of a request path, see [`parse_update_path`]
+#[derive(Debug, Default, PartialEq)]
+pub struct UpdatePath<'a> {
+    /// The request is for the updates and not for the base repository
+    pub updates: bool,
+    /// Point release like `2.6` or `1.3.8`, if the path contains it
+    pub point_release: Option<&'a str>,
+}
+
+fn is_version(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_digit()) && s.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Parses the part of a request path after the release prefix. The
+/// repositories of a release are laid out as `updates/...` and
+/// `update.2.6/...` (2.x and later) or `1.3.8/updates/...` and
+/// `1.3.8/base/...` (1.3.x). Everything else belongs to the base
+/// repository.
+pub fn parse_update_path(rest: &str) -> UpdatePath<'_> {
+    let mut segments = rest.split('/');
+    let first = segments.next().unwrap_or_default();
+    if first == "updates" {
+        return UpdatePath {
+            updates: true,
+            point_release: None,
+        };
+    }
+    if let Some(version) = first.strip_prefix("update.").filter(|v| is_version(v)) {
+        return UpdatePath {
+            updates: true,
+            point_release: Some(version),
+        };
+    }
+    if is_version(first) {
+        return UpdatePath {
+            updates: segments.next() == Some("updates"),
+            point_release: Some(first),
+        };
+    }
+    UpdatePath::default()
+}
+
+/// Client family, client version and operating system of a user agent, see
+/// [`parse_user_agent`]
+#[derive(Debug, Default, PartialEq)]
+pub struct UserAgent<'a> {
+    /// Client family like `libdnf`, `yum`, `zypper` or `crawler`
+    pub client: &'static str,
+    pub version: Option<&'a str>,
+    pub os: Option<&'a str>,
+    pub os_version: Option<&'a str>,
+    /// Machine architecture like `x86_64`
+    pub machine: Option<&'a str>,
+}
+
+/// Splits `Rocky Linux 9.3` into `Rocky Linux` and `9.3`
+fn split_os(os: &str) -> (Option<&str>, Option<&str>) {
+    let os = os.trim();
+    match os.rsplit_once(' ') {
+        Some((name, version)) if is_version(version) => (Some(name), Some(version)),
+        _ if os.is_empty() => (None, None),
+        _ => (Some(os), None),
+    }
+}
+
+/// Operating system, its version and the machine architecture
+type Os<'a> = (Option<&'a str>, Option<&'a str>, Option<&'a str>);
+
+/// Splits the `(Rocky Linux 9.3; generic; Linux.x86_64)` comment of the
+/// dnf user agents into the operating system, its version and the machine
+/// architecture
+fn split_dnf_os(comment: &str) -> Os<'_> {
+    let comment = match comment.trim().strip_prefix('(') {
+        Some(c) => c.split(')').next().unwrap_or_default(),
+        None => return (None, None, None),
+    };
+    let mut fields = comment.split(';').map(str::trim);
+    let (os, os_version) = split_os(fields.next().unwrap_or_default());
+    let machine = fields
+        .nth(1)
+        .and_then(|platform| platform.split_once('.'))
+        .map(|(_, machine)| machine)
+        .filter(|m| !m.is_empty());
+    (os, os_version, machine)
+}
+
+/// Splits the `openSUSE-Leap-15.5-x86_64` or `SLE-15-SP4-x86_64` suffix of
+/// the libzypp user agents into the operating system, its version and the
+/// machine architecture
+fn split_zypp_os(product: &str) -> Os<'_> {
+    let (product, machine) = match product.rsplit_once('-') {
+        Some((p, machine)) => (p, Some(machine).filter(|m| !m.is_empty())),
+        None => (product, None),
+    };
+    let start = product
+        .match_indices('-')
+        .find(|(i, _)| product[i + 1..].starts_with(|c: char| c.is_ascii_digit()));
+    match start {
+        Some((i, _)) => (Some(&product[..i]), Some(&product[i + 1..]), machine),
+        None if product.is_empty() => (None, None, None),
+        None => (Some(product), None, machine),
+    }
+}
+
+/// Returns the major version of an operating system version, `8` for `8.9`
+/// and `15` for `15-SP4`
+pub fn major_version(version: &str) -> &str {
+    version.split(['.', '-']).next().unwrap_or_default()
+}
+
+/// Returns the version of the first `name/version` token of `user_agent`
+/// whose name is `name` (ignoring case)
+fn product_version<'a>(user_agent: &'a str, name: &str) -> Option<&'a str> {
+    user_agent.split(' ').find_map(|token| {
+        let (n, v) = token.split_once('/')?;
+        (n.eq_ignore_ascii_case(name) && !v.is_empty()).then_some(v)
+    })
+}
+
+/// Automated clients which are not package managers or users
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Bot {
+    /// Search engines and other crawlers, and every request for
+    /// `/robots.txt` whatever its user agent (the other requests of such a
+    /// client are not affected)
+    Crawler,
+    /// Uptime and availability monitoring
+    Monitor,
+    /// Security and internet-wide scanners
+    Scanner,
+    /// Clients which only requested the start page or `/robots.txt` within a
+    /// year, only known once the report is created (see [`is_probe_path`])
+    Probe,
+}
+
+impl Bot {
+    pub fn name(self) -> &'static str {
+        match self {
+            Bot::Crawler => "crawler",
+            Bot::Monitor => "monitor",
+            Bot::Scanner => "scanner",
+            Bot::Probe => "probe",
+        }
+    }
+}
+
+/// Lower case user agent substrings of named monitoring services and tools
+const MONITOR_PATTERNS: [&str; 10] = [
+    "uptimerobot",
+    "pingdom",
+    "statuscake",
+    "site24x7",
+    "check_http",
+    "nagios",
+    "zabbix",
+    "blackbox exporter",
+    "updown.io",
+    "newrelicpinger",
+];
+
+/// Lower case user agent substrings of security and research scanners
+const SCANNER_PATTERNS: [&str; 10] = [
+    "nmap",
+    "masscan",
+    "zgrab",
+    "nuclei",
+    "sqlmap",
+    "nikto",
+    "censysinspect",
+    "expanse",
+    "internet-measurement",
+    "l9explore",
+];
+
+/// Lower case user agent substrings of crawlers. Only named crawlers, not
+/// generic words like `bot`, which also matches browsers on e.g. Cubot
+/// phones.
+const CRAWLER_PATTERNS: [&str; 21] = [
+    "googlebot",
+    "bingbot",
+    "duckduckbot",
+    "yandexbot",
+    "applebot",
+    "petalbot",
+    "dotbot",
+    "ahrefsbot",
+    "semrushbot",
+    "mj12bot",
+    "dataforseobot",
+    "claudebot",
+    "gptbot",
+    "amazonbot",
+    "ccbot",
+    "baiduspider",
+    "bytespider",
+    "slurp",
+    "ia_archiver",
+    "archive.org",
+    "facebookexternalhit",
+];
+
+/// Case insensitive matchers of the bot patterns. Monitors first, e.g.
+/// UptimeRobot is no crawler.
+static BOT_MATCHERS: LazyLock<[(Bot, AhoCorasick); 3]> = LazyLock::new(|| {
+    let matcher = |patterns: &[&str]| {
+        AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .build(patterns)
+            .expect("valid bot patterns")
+    };
+    [
+        (Bot::Monitor, matcher(&MONITOR_PATTERNS)),
+        (Bot::Scanner, matcher(&SCANNER_PATTERNS)),
+        (Bot::Crawler, matcher(&CRAWLER_PATTERNS)),
+    ]
+});
+
+fn bot_user_agent(user_agent: &str) -> Option<Bot> {
+    BOT_MATCHERS
+        .iter()
+        .find(|(_, matcher)| matcher.is_match(user_agent))
+        .map(|(bot, _)| *bot)
+}
+
+/// Returns the kind of bot which sent a request, recognized by the user
+/// agent or by a request for `/robots.txt`
+pub fn detect_bot(user_agent: Option<&str>, path: &str) -> Option<Bot> {
+    if let Some(bot) = user_agent.and_then(bot_user_agent) {
+        return Some(bot);
+    }
+    match path.split(['?', '#']).next() == Some("/robots.txt") {
+        true => Some(Bot::Crawler),
+        false => None,
+    }
+}
+
+/// Returns true for requests of the start page or `/robots.txt`. Clients
+/// which send nothing else are most likely bots.
+pub fn is_probe_path(path: &str) -> bool {
+    matches!(
+        path.split(['?', '#']).next(),
+        Some("/" | "/robots.txt" | "/index.html")
+    )
+}
+
+/// Classifies a user agent into a client family, its version and, where the
+/// user agent contains it, the operating system and its version. Recognized
+/// are the package managers (libdnf, dnf, dnf5, yum, zypper, PackageKit),
+/// download tools (urlgrabber, curl, wget, pip, Ansible, Pulp), bots (see
+/// [`Bot`]) and browsers. Everything else is `other`, missing user agents are
+/// `unknown`.
+pub fn parse_user_agent(user_agent: Option<&str>) -> UserAgent<'_> {
+    let user_agent = match user_agent.map(str::trim) {
+        Some(u) if !u.is_empty() => u,
+        _ => {
+            return UserAgent {
+                client: "unknown",
+                ..Default::default()
+            }
+        }
+    };
+    let lower = user_agent.to_ascii_lowercase();
+    let (name, rest) = user_agent.split_once(' ').unwrap_or((user_agent, ""));
+    let (name, version) = match name.split_once('/') {
+        Some((n, v)) => (n, Some(v).filter(|v| !v.is_empty())),
+        None => (name, None),
+    };
+
+    // libdnf (Rocky Linux 9.3; generic; Linux.x86_64)
+    // dnf/4.20.0 (openEuler 24.03; generic; Linux.x86_64)
+    if matches!(name, "libdnf" | "dnf" | "libdnf5" | "dnf5") {
+        let (os, os_version, machine) = split_dnf_os(rest);
+        let client = match name {
+            "libdnf" => "libdnf",
+            "dnf" => "dnf",
+            _ => "dnf5",
+        };
+        return UserAgent {
+            client,
+            version,
+            os,
+            os_version,
+            machine,
+        };
+    }
+    // ZYpp 17.31.15 (curl 8.0.1) openSUSE-Leap-15.5-x86_64
+    if name == "ZYpp" {
+        let version = rest.split(' ').next().filter(|v| is_version(v));
+        let (os, os_version, machine) = match rest.rsplit_once(' ') {
+            Some((_, product)) if !product.ends_with(')') => split_zypp_os(product),
+            _ => (None, None, None),
+        };
+        return UserAgent {
+            client: "zypper",
+            version,
+            os,
+            os_version,
+            machine,
+        };
+    }
+    // urlgrabber/3.10 yum/3.4.3
+    if let Some(version) = product_version(user_agent, "yum") {
+        return UserAgent {
+            client: "yum",
+            version: Some(version),
+            ..Default::default()
+        };
+    }
+    if let Some(bot) = bot_user_agent(user_agent) {
+        return UserAgent {
+            client: bot.name(),
+            ..Default::default()
+        };
+    }
+    let client = match lower.split(['/', ' ']).next().unwrap_or_default() {
+        "urlgrabber" => "urlgrabber",
+        "curl" => "curl",
+        "wget" => "wget",
+        "pip" => "pip",
+        "ansible-httpget" => "ansible",
+        "pulpcore" => "pulp",
+        n if n.starts_with("packagekit") => "PackageKit",
+        "mozilla" => "browser",
+        _ => "other",
+    };
+    let version = match client {
+        "browser" | "other" => None,
+        _ => version,
+    };
+    UserAgent {
+        client,
+        version,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::Write;
+use std::sync::Mutex;
+
+use chrono::{
+    DateTime,
+    FixedOffset,
+    NaiveDate,
+    NaiveDateTime,
+    TimeDelta,
+    TimeZone,
+};
+
+use crate::config::Config;
+use crate::parser::{
+    self,
+    LogFormat,
+};
+
+/// Request path extensions which are not counted by default
+pub const DEFAULT_ASSET_EXTENSIONS: [&str; 6] = ["png", "gif", "ico", "jpg", "jpeg", "svg"];
+
+/// Static assets (images, icons) which are not counted as repository
+/// requests
+pub struct AssetFilter {
+    extensions: Vec<String>,
+    prefixes: Vec<String>,
+    legacy: bool,
+}
+
+impl AssetFilter {
+    pub fn new(extensions: &[String], prefixes: &[String], legacy: bool) -> AssetFilter {
+        AssetFilter {
+            extensions: extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_ascii_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect(),
+            prefixes: prefixes.to_vec(),
+            legacy,
+        }
+    }
+
+    /// The filter of earlier versions which skipped any line containing
+    /// 'png' or 'gif'. This also dropped RPMs like libpng-devel or giflib.
+    pub fn is_legacy_asset(&self, line: &[u8]) -> bool {
+        self.legacy
+            && line
+                .windows(3)
+                .any(|window| window == b"png" || window == b"gif")
+    }
+
+    pub fn is_asset(&self, path: &str) -> bool {
+        if self.legacy {
+            return false;
+        }
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        if self.prefixes.iter().any(|p| path.starts_with(p.as_str())) {
+            return true;
+        }
+        let file_name = path.rsplit('/').next().unwrap_or_default();
+        match file_name.rsplit_once('.') {
+            Some((_, extension)) => self
+                .extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension)),
+            None => false,
+        }
+    }
+}
+
+impl Default for AssetFilter {
+    fn default() -> AssetFilter {
+        let extensions: Vec<String> = DEFAULT_ASSET_EXTENSIONS
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        AssetFilter::new(&extensions, &[], false)
+    }
+}
+
+/// Timezone in which the requests are reported
+pub enum ReportingTimezone {
+    Named(chrono_tz::Tz),
+    Fixed(FixedOffset),
+}
+
+impl ReportingTimezone {
+    pub fn new(timezone: &str) -> Result<ReportingTimezone, String> {
+        if let Ok(tz) = timezone.parse::<chrono_tz::Tz>() {
+            return Ok(ReportingTimezone::Named(tz));
+        }
+        match DateTime::parse_from_str(&format!("2000-01-01 00:00 {timezone}"), "%F %R %:z") {
+            Ok(d) => Ok(ReportingTimezone::Fixed(*d.offset())),
+            Err(_) => Err(format!("Unknown timezone '{timezone}'")),
+        }
+    }
+
+    pub fn local(&self, time: &DateTime<FixedOffset>) -> NaiveDateTime {
+        match self {
+            ReportingTimezone::Named(tz) => time.with_timezone(tz).naive_local(),
+            ReportingTimezone::Fixed(offset) => time.with_timezone(offset).naive_local(),
+        }
+    }
+
+    /// Returns the start of `date`. Where a daylight saving time change
+    /// skips midnight (e.g. America/Santiago), the day starts with the first
+    /// valid minute after the gap.
+    pub fn midnight(&self, date: NaiveDate) -> Option<DateTime<FixedOffset>> {
+        let midnight = date.and_hms_opt(0, 0, 0)?;
+        match self {
+            ReportingTimezone::Named(tz) => (0..24 * 60).find_map(|minutes| {
+                tz.from_local_datetime(&(midnight + TimeDelta::minutes(minutes)))
+                    .earliest()
+                    .map(|t| t.fixed_offset())
+            }),
+            ReportingTimezone::Fixed(offset) => offset
+                .from_local_datetime(&midnight)
+                .single()
+                .map(|t| t.fixed_offset()),
+        }
+    }
+}
+
+impl Default for ReportingTimezone {
+    fn default() -> ReportingTimezone {
+        ReportingTimezone::Named(chrono_tz::UTC)
+    }
+}
+
+/// Time window (since, until) of the requests which are counted
+#[derive(Default)]
+pub struct DateRange {
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+}
+
+impl DateRange {
+    /// Both limits are either a date (YYYY-MM-DD in `timezone`) or an RFC
+    /// 3339 timestamp. A date as `until` includes the whole day.
+    pub fn new(
+        since: Option<&str>,
+        until: Option<&str>,
+        timezone: &ReportingTimezone,
+    ) -> Result<DateRange, String> {
+        let parse = |value: Option<&str>, next_day: bool| {
+            let value = match value {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+            if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+                return Ok(Some(time));
+            }
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| match next_day {
+                    true => d.succ_opt(),
+                    false => Some(d),
+                })
+                .and_then(|d| timezone.midnight(d))
+                .map(Some)
+                .ok_or(format!("Invalid date or RFC 3339 timestamp '{value}'"))
+        };
+
+        Ok(DateRange {
+            since: parse(since, false)?,
+            until: parse(until, true)?,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    pub fn is_before(&self, time: &DateTime<FixedOffset>) -> bool {
+        self.since.is_some_and(|since| *time < since)
+    }
+
+    pub fn is_after(&self, time: &DateTime<FixedOffset>) -> bool {
+        self.until.is_some_and(|until| *time >= until)
+    }
+
+    pub fn contains(&self, time: &DateTime<FixedOffset>) -> bool {
+        !self.is_before(time) && !self.is_after(time)
+    }
+
+    /// Returns true if the first and the last line of a chunk are both
+    /// before or both after the time window. Access logs are written in
+    /// chronological order, so none of the lines in between are counted.
+    pub fn skip_chunk(&self, lines: &[u8], log_format: &LogFormat) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        let mut non_empty = lines.split(|c| *c == b'\n').filter(|l| !l.is_empty());
+        let timestamp = |line: Option<&[u8]>| {
+            let line = String::from_utf8_lossy(line?);
+            parser::parse_timestamp(log_format.parse(&line)?.timestamp)
+        };
+        let first = match timestamp(non_empty.next()) {
+            Some(t) => t,
+            None => return false,
+        };
+        let last = match timestamp(non_empty.next_back()) {
+            Some(t) => t,
+            None => return false,
+        };
+        (self.is_before(&first) && self.is_before(&last))
+            || (self.is_after(&first) && self.is_after(&last))
+    }
+}
+
+/// Settings used while processing the lines of the access logs
+#[derive(Default)]
+pub struct Settings {
+    pub log_format: LogFormat,
+    pub assets: AssetFilter,
+    pub timezone: ReportingTimezone,
+    pub date_range: DateRange,
+    /// Rules to recognize the releases and distributions
+    pub config: Config,
+    /// Requests of bots are only counted as bot traffic
+    pub exclude_bots: bool,
+    /// Receives every rejected line together with the reason
+    pub rejected_output: Option<Mutex<Box<dyn Write + Send>>>,
+}
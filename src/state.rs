@@ -0,0 +1,164 @@
+use std::fs::File;
+use std::io::{
+    BufReader,
+    BufWriter,
+    Write,
+};
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::aggregator::Aggregator;
+use crate::settings::Settings;
+
+/// Incremented whenever the layout of the state file changes
+pub const STATE_VERSION: u32 = 2;
+
+/// Number of bytes at the beginning of an input used to recognize it again,
+/// even after it has been rotated (renamed or compressed)
+pub const FINGERPRINT_LENGTH: usize = 4096;
+
+/// 64 bit FNV-1a hash. Unlike the hashers of the standard library it does
+/// not change between Rust versions.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn release_keys(settings: &Settings) -> Vec<String> {
+    settings
+        .config
+        .releases
+        .iter()
+        .map(|r| r.key.clone())
+        .collect()
+}
+
+/// Records how much of an input has already been counted
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InputState {
+    /// Name of the input when it was last processed
+    pub name: String,
+    /// Hash of the first `fingerprint_length` (decompressed) bytes
+    pub fingerprint: u64,
+    pub fingerprint_length: usize,
+    /// Number of (decompressed) bytes which have been counted
+    pub offset: u64,
+}
+
+impl InputState {
+    fn matches(&self, prefix: &[u8]) -> bool {
+        self.fingerprint_length > 0
+            && prefix.len() >= self.fingerprint_length
+            && fnv1a(&prefix[..self.fingerprint_length]) == self.fingerprint
+    }
+}
+
+/// Aggregated results of earlier runs together with the inputs which have
+/// been counted so far
+#[derive(Deserialize, Serialize)]
+pub struct State {
+    pub version: u32,
+    /// Keys of the configured releases. The counts of the aggregator are
+    /// stored in the order of these releases.
+    pub releases: Vec<String>,
+    /// Configured architectures, in the order used by the aggregator
+    pub architectures: Vec<String>,
+    pub inputs: Vec<InputState>,
+    pub aggregator: Aggregator,
+}
+
+impl State {
+    pub fn new(settings: Arc<Settings>) -> State {
+        State {
+            version: STATE_VERSION,
+            releases: release_keys(&settings),
+            architectures: settings.config.architectures.clone(),
+            inputs: Vec::new(),
+            aggregator: Aggregator::new(settings),
+        }
+    }
+
+    /// Loads the state of an earlier run. The same settings (log format,
+    /// timezone, filters) should be used for all runs and the releases and
+    /// architectures of the configuration have to be the same.
+    pub fn load(path: &Path, settings: Arc<Settings>) -> Result<State, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut state: State = serde_json::from_reader(reader)?;
+        if state.version != STATE_VERSION {
+            return Err(format!(
+                "Unsupported state version {} (expected {STATE_VERSION})",
+                state.version
+            )
+            .into());
+        }
+        let releases = release_keys(&settings);
+        if state.releases != releases {
+            return Err(format!(
+                "State was written with the releases {:?}, the configuration has {:?}",
+                state.releases, releases
+            )
+            .into());
+        }
+        if state.architectures != settings.config.architectures {
+            return Err(format!(
+                "State was written with the architectures {:?}, the configuration has {:?}",
+                state.architectures, settings.config.architectures
+            )
+            .into());
+        }
+        state.aggregator.set_settings(settings);
+        Ok(state)
+    }
+
+    /// Writes the state to a temporary file which then replaces `path`, so
+    /// an interrupted run does not leave a truncated state behind.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    /// Adds the results of another state, e.g. of another server. Clients
+    /// which are in both states are only counted once per month and year.
+    pub fn merge(&mut self, other: State) {
+        self.inputs.extend(other.inputs);
+        self.aggregator.merge(other.aggregator);
+    }
+
+    /// Returns the number of bytes of the input starting with `prefix`
+    /// which have already been counted
+    pub fn processed(&self, prefix: &[u8]) -> u64 {
+        self.inputs
+            .iter()
+            .filter(|i| i.matches(prefix))
+            .map(|i| i.offset)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Records that the input starting with `prefix` has been counted up
+    /// to `offset`
+    pub fn record(&mut self, name: &str, prefix: &[u8], offset: u64) {
+        if prefix.is_empty() {
+            return;
+        }
+        self.inputs.retain(|i| !i.matches(prefix));
+        let prefix = &prefix[..prefix.len().min(FINGERPRINT_LENGTH)];
+        self.inputs.push(InputState {
+            name: name.to_string(),
+            fingerprint: fnv1a(prefix),
+            fingerprint_length: prefix.len(),
+            offset,
+        });
+    }
+}
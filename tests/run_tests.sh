@@ -86,6 +86,515 @@ if [ "${OPENEULER_2025}" != "1" ]; then
 	exit 1
 fi
 
+# Requests, unique clients and bytes per release, distribution and period
+OHPC4_EL_2025=$(jq -c '.requests_per_release[] | select(.release == "ohpc4" and .distribution == "RHEL" and .period == "2025") | [.requests, .unique, .size]' "${DEST}"/stats.json)
+if [ "${OHPC4_EL_2025}" != "[3,3,3584]" ]; then
+	echo "ERROR: OpenHPC 4.x RHEL requests for 2025 returned unexpected value: ${OHPC4_EL_2025}"
+	jq '.' "${DEST}"/stats.json
+	exit 1
+fi
+
+# Most downloaded packages by name and version
+TOP_PACKAGE=$(jq -c '[.top_package_versions[] | select(.year == 2024 and .release == "ohpc1")][0] | [.name, .version, .count]' "${DEST}"/stats.json)
+if [ "${TOP_PACKAGE}" != '["scalapack-gnu7-mvapich2-ohpc","2.0.2",4]' ]; then
+	echo "ERROR: most downloaded package returned unexpected value: ${TOP_PACKAGE}"
+	jq '.' "${DEST}"/stats.json
+	exit 1
+fi
+
+# Point release adoption
+POINT_RELEASE=$(jq -c '.requests_per_update[] | select(.release == "ohpc2" and .point_release == "2.6.1" and .period == "2023") | [.requests, .unique]' "${DEST}"/stats.json)
+if [ "${POINT_RELEASE}" != "[8,2]" ]; then
+	echo "ERROR: requests per point release returned unexpected value: ${POINT_RELEASE}"
+	jq '.' "${DEST}"/stats.json
+	exit 1
+fi
+
+# Daily, weekly and hour of the day/day of the week aggregation
+DAY_2025=$(jq -c '.requests_per_day[] | select(.period == "2025-01-15") | [.requests, .unique, .size]' "${DEST}"/stats.json)
+if [ "${DAY_2025}" != "[5,5,6144]" ]; then
+	echo "ERROR: requests per day returned unexpected value: ${DAY_2025}"
+	jq '.' "${DEST}"/stats.json
+	exit 1
+fi
+WEEK_2024=$(jq -c '.requests_per_week[] | select(.period == "2024-W17") | [.requests, .unique]' "${DEST}"/stats.json)
+if [ "${WEEK_2024}" != "[122,22]" ]; then
+	echo "ERROR: requests per week returned unexpected value: ${WEEK_2024}"
+	jq '.' "${DEST}"/stats.json
+	exit 1
+fi
+WEDNESDAY_10=$(jq '.requests_per_weekday_and_hour[2][10]' "${DEST}"/stats.json)
+if [ "${WEDNESDAY_10}" != "5" ]; then
+	echo "ERROR: requests per day of the week and hour returned unexpected value: ${WEDNESDAY_10}"
+	jq '.' "${DEST}"/stats.json
+	exit 1
+fi
+
+# Compressed input files have to give the same results as the plain text file
+for COMPRESS in gzip bzip2 xz zstd; do
+	if ! command -v "${COMPRESS}" > /dev/null; then
+		continue
+	fi
+	COMPRESSED_DEST=$(mktemp -d)
+	"${COMPRESS}" -c tests/access_log > "${COMPRESSED_DEST}/access_log.compressed"
+	"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${COMPRESSED_DEST}" "${COMPRESSED_DEST}/access_log.compressed"
+	if ! cmp "${DEST}/stats.json" "${COMPRESSED_DEST}/stats.json"; then
+		echo "ERROR: ${COMPRESS} compressed input returned unexpected values"
+		jq '.' "${COMPRESSED_DEST}"/stats.json
+		exit 1
+	fi
+	rm -rf "${COMPRESSED_DEST}"
+done
+
+# Directories, glob patterns and stdin have to give the same results
+INPUT_DEST=$(mktemp -d)
+mkdir -p "${INPUT_DEST}/logs/rotated"
+cp tests/access_log "${INPUT_DEST}/logs/rotated/access_log-20240101"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${INPUT_DEST}" "${INPUT_DEST}/logs"
+cmp "${DEST}/stats.json" "${INPUT_DEST}/stats.json"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${INPUT_DEST}" "${INPUT_DEST}/logs/*/access_log*"
+cmp "${DEST}/stats.json" "${INPUT_DEST}/stats.json"
+gzip -c tests/access_log | "${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${INPUT_DEST}" -
+cmp "${DEST}/stats.json" "${INPUT_DEST}/stats.json"
+# Rotated logs are read from the oldest to the newest, log by log
+mkdir -p "${INPUT_DEST}/order"
+for LOG in access_log.2 other_log.1 access_log.1 access_log other_log; do
+	echo "${LOG}" > "${INPUT_DEST}/order/${LOG}"
+done
+ORDER=$("${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${INPUT_DEST}" "${INPUT_DEST}/order" |
+	sed -n "s#.*Using '${INPUT_DEST}/order/\(.*\)' as input.*#\1#p" | paste -sd ' ')
+if [ "${ORDER}" != "access_log.2 access_log.1 access_log other_log.1 other_log" ]; then
+	echo "ERROR: rotated logs were read in an unexpected order: ${ORDER}"
+	exit 1
+fi
+rm -rf "${INPUT_DEST}"
+
+# Quoted fields with spaces, doubled spaces, '-' sizes and the common log format
+COMBINED_DEST=$(mktemp -d)
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${COMBINED_DEST}" tests/access_log_combined
+OVERALL_2019=$(jq '.unique_visitors_per_year[0].overall' "${COMBINED_DEST}"/stats.json)
+if [ "${OVERALL_2019}" != "4" ]; then
+	echo "ERROR: combined log format overall count returned unexpected value: ${OVERALL_2019}"
+	jq '.' "${COMBINED_DEST}"/stats.json
+	exit 1
+fi
+SIZE_2019=$(jq '.size_per_year[0].size' "${COMBINED_DEST}"/stats.json)
+if [ "${SIZE_2019}" != "3826" ]; then
+	echo "ERROR: combined log format size returned unexpected value: ${SIZE_2019}"
+	jq '.' "${COMBINED_DEST}"/stats.json
+	exit 1
+fi
+ROCKY_2019=$(jq '.result_libdnf[] | select(.year == 2019 and .name == "Rocky Linux") | .count' "${COMBINED_DEST}"/stats.json)
+if [ "${ROCKY_2019}" != "1" ]; then
+	echo "ERROR: combined log format Rocky Linux count returned unexpected value: ${ROCKY_2019}"
+	jq '.' "${COMBINED_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${COMBINED_DEST}"
+
+# nginx and custom Apache log formats have to give the same results
+FORMAT_DEST=$(mktemp -d)
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${FORMAT_DEST}" --log-format nginx-combined tests/access_log
+# The comment and empty lines in the test data are rejected for different reasons
+diff <(jq 'del(.rejected_lines)' "${DEST}/stats.json") <(jq 'del(.rejected_lines)' "${FORMAT_DEST}/stats.json")
+# Directives which are not counted, like %D and X-Forwarded-For, are skipped
+awk 'NF { print $0 " 1234 \"10.1.1.1, 10.2.2.2\"" }' tests/access_log > "${FORMAT_DEST}/access_log"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${FORMAT_DEST}" \
+	--log-format '%h %l %u %t \"%r\" %>s %b \"%{Referer}i\" \"%{User-Agent}i\" %D \"%{X-Forwarded-For}i\"' \
+	"${FORMAT_DEST}/access_log"
+diff <(jq 'del(.rejected_lines)' "${DEST}/stats.json") <(jq 'del(.rejected_lines)' "${FORMAT_DEST}/stats.json")
+rm -rf "${FORMAT_DEST}"
+
+# Lines which are not valid UTF-8 are counted and do not abort the run
+UTF8_DEST=$(mktemp -d)
+cp tests/access_log_combined "${UTF8_DEST}/access_log"
+printf '10.0.0.5 - - [01/Mar/2019:10:00:04 +0000] "GET /OpenHPC/2/EL_8/caf\xe9 HTTP/1.1" 200 10 "-" "agent \xff\xfe"\n' >> "${UTF8_DEST}/access_log"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${UTF8_DEST}" "${UTF8_DEST}/access_log"
+INVALID_UTF8=$(jq '.invalid_utf8_lines' "${UTF8_DEST}"/stats.json)
+OVERALL_2019=$(jq '.unique_visitors_per_year[0].overall' "${UTF8_DEST}"/stats.json)
+if [ "${INVALID_UTF8}" != "1" ] || [ "${OVERALL_2019}" != "5" ]; then
+	echo "ERROR: invalid UTF-8 lines returned unexpected values: ${INVALID_UTF8} ${OVERALL_2019}"
+	jq '.' "${UTF8_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${UTF8_DEST}"
+
+# Rejected lines are counted per reason and written to the rejected lines file
+REJECTED_DEST=$(mktemp -d)
+cp tests/access_log_combined "${REJECTED_DEST}/access_log"
+cat >> "${REJECTED_DEST}/access_log" <<EOF
+
+10.0.0.6 - - [01/Mar/2019:10:00:05 +0000] "GET /favicon.png HTTP/1.1" 200 10 "-" "-"
+10.0.0.6 - - [01/Foo/2019:10:00:05 +0000] "GET / HTTP/1.1" 200 10 "-" "-"
+10.0.0.6 - - [01/Mar/20xx:10:00:05 +0000] "GET / HTTP/1.1" 200 10 "-" "-"
+10.0.0.6 - - [01/Mar/2019:10:00:05 +0000] "GET /GET HTTP/1.1" 200 10 "-" "-"
+10.0.0.6 -
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${REJECTED_DEST}" \
+	--rejected-output "${REJECTED_DEST}/rejected" "${REJECTED_DEST}/access_log"
+REJECTED=$(jq -c '.rejected_lines' "${REJECTED_DEST}"/stats.json)
+if [ "${REJECTED}" != '{"double_get":1,"empty":1,"incomplete":1,"invalid_timestamp":2,"static_asset":1}' ]; then
+	echo "ERROR: rejected lines returned unexpected values: ${REJECTED}"
+	exit 1
+fi
+if [ "$(wc -l < "${REJECTED_DEST}/rejected")" != "6" ] || ! grep -q "^invalid_timestamp	" "${REJECTED_DEST}/rejected"; then
+	echo "ERROR: rejected lines file has unexpected content"
+	cat "${REJECTED_DEST}/rejected"
+	exit 1
+fi
+rm -rf "${REJECTED_DEST}"
+
+# Static assets are detected by the request path and not by substrings
+ASSET_DEST=$(mktemp -d)
+cat > "${ASSET_DEST}/access_log" <<EOF
+10.0.0.7 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/x86_64/libpng-devel-1.6.34-5.el8.x86_64.rpm HTTP/1.1" 200 10 "-" "-"
+10.0.0.8 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/x86_64/giflib-5.1.4-3.el8.x86_64.rpm HTTP/1.1" 200 10 "-" "-"
+10.0.0.9 - - [01/Mar/2019:10:00:00 +0000] "GET /favicon.ico?v=2 HTTP/1.1" 200 10 "-" "-"
+10.0.0.9 - - [01/Mar/2019:10:00:00 +0000] "GET /icons/folder HTTP/1.1" 200 10 "-" "-"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${ASSET_DEST}" --asset-prefixes /icons/ "${ASSET_DEST}/access_log"
+ASSETS=$(jq '.rejected_lines.static_asset' "${ASSET_DEST}"/stats.json)
+OVERALL_2019=$(jq '.unique_visitors_per_year[0].overall' "${ASSET_DEST}"/stats.json)
+if [ "${ASSETS}" != "2" ] || [ "${OVERALL_2019}" != "2" ]; then
+	echo "ERROR: static asset filter returned unexpected values: ${ASSETS} ${OVERALL_2019}"
+	jq '.' "${ASSET_DEST}"/stats.json
+	exit 1
+fi
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${ASSET_DEST}" --legacy-asset-filter "${ASSET_DEST}/access_log"
+ASSETS=$(jq '.rejected_lines.static_asset' "${ASSET_DEST}"/stats.json)
+if [ "${ASSETS}" != "2" ]; then
+	echo "ERROR: legacy static asset filter returned unexpected value: ${ASSETS}"
+	jq '.' "${ASSET_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${ASSET_DEST}"
+
+# User agents are classified into client families and operating systems
+AGENT_DEST=$(mktemp -d)
+cat > "${AGENT_DEST}/access_log" <<EOF
+10.0.0.10 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/Leap_15/repodata/repomd.xml HTTP/1.1" 200 10 "-" "ZYpp 17.31.15 (curl 8.0.1) openSUSE-Leap-15.5-x86_64"
+10.0.0.11 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/Leap_15/repodata/repomd.xml HTTP/1.1" 200 10 "-" "ZYpp 17.31.8 (curl 7.79.1) SLE-15-SP4-x86_64"
+10.0.0.12 - - [01/Mar/2019:10:00:00 +0000] "GET /ohpc-1.3/CentOS_7/repodata/repomd.xml HTTP/1.1" 200 10 "-" "urlgrabber/3.10 yum/3.4.3"
+10.0.0.13 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "curl/7.61.1"
+10.0.0.14 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/3/EL_9/repodata/repomd.xml HTTP/1.1" 200 10 "-" "dnf/4.20.0 (openEuler 24.03; generic; Linux.x86_64)"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${AGENT_DEST}" "${AGENT_DEST}/access_log"
+AGENTS=$(jq -c '[.requests_per_user_agent[] | select(.period == "2019" and .release == null) | [.client, .version, .os, .os_version]]' "${AGENT_DEST}"/stats.json)
+if [ "${AGENTS}" != '[["curl","7.61.1",null,null],["dnf","4.20.0","openEuler","24.03"],["yum","3.4.3",null,null],["zypper","17.31.15","openSUSE-Leap","15.5"],["zypper","17.31.8","SLE","15-SP4"]]' ]; then
+	echo "ERROR: user agent classification returned unexpected values: ${AGENTS}"
+	jq '.' "${AGENT_DEST}"/stats.json
+	exit 1
+fi
+AGENTS=$(jq -c '[.requests_per_user_agent[] | select(.period == "2019" and .release == "ohpc3") | [.os, .os_version, .machine]]' "${AGENT_DEST}"/stats.json)
+if [ "${AGENTS}" != '[["openEuler","24.03","x86_64"]]' ]; then
+	echo "ERROR: user agents per release returned unexpected values: ${AGENTS}"
+	jq '.' "${AGENT_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${AGENT_DEST}"
+
+# Bots are reported separately and excluded with --exclude-bots, a client
+# which only requested the start page (10.0.0.22) is then a probe
+BOT_DEST=$(mktemp -d)
+cat > "${BOT_DEST}/access_log" <<EOF
+10.0.0.20 - - [01/Mar/2019:10:00:00 +0000] "GET / HTTP/1.1" 200 10 "-" "Mozilla/5.0+(compatible; UptimeRobot/2.0; http://www.uptimerobot.com/)"
+10.0.0.21 - - [01/Mar/2019:10:00:00 +0000] "GET /robots.txt HTTP/1.1" 200 10 "-" "Mozilla/5.0"
+10.0.0.21 - - [01/Mar/2019:10:00:01 +0000] "GET /OpenHPC/2/EL_8/ HTTP/1.1" 200 10 "-" "Mozilla/5.0"
+10.0.0.22 - - [01/Mar/2019:10:00:00 +0000] "GET / HTTP/1.1" 200 10 "-" "Mozilla/5.0"
+10.0.0.23 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "zgrab/0.x"
+10.0.0.24 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Rocky Linux 8.9; generic; Linux.x86_64)"
+10.0.0.25 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/ HTTP/1.1" 200 10 "-" "Mozilla/5.0 (Linux; Android 10; CUBOT X30) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Mobile Safari/537.36"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${BOT_DEST}" --exclude-bots "${BOT_DEST}/access_log"
+BOTS=$(jq -c '[[.bot_traffic[] | select(.period == "2019") | [.bot, .requests]], .probe_clients_per_year, .unique_visitors_per_year[0].overall]' "${BOT_DEST}"/stats.json)
+if [ "${BOTS}" != '[[["crawler",1],["monitor",1],["scanner",1],["probe",1]],{"2019":1},3]' ]; then
+	echo "ERROR: bot detection returned unexpected values: ${BOTS}"
+	jq '.' "${BOT_DEST}"/stats.json
+	exit 1
+fi
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${BOT_DEST}" "${BOT_DEST}/access_log"
+BOTS=$(jq -c '[[.bot_traffic[] | select(.period == "2019") | [.bot, .requests]], .probe_clients_per_year, .unique_visitors_per_year[0].overall]' "${BOT_DEST}"/stats.json)
+if [ "${BOTS}" != '[[["crawler",1],["monitor",1],["scanner",1]],{"2019":2},6]' ]; then
+	echo "ERROR: bots without --exclude-bots returned unexpected values: ${BOTS}"
+	jq '.' "${BOT_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${BOT_DEST}"
+
+# Architectures are taken from package file names and directories
+ARCH_DEST=$(mktemp -d)
+cat > "${ARCH_DEST}/access_log" <<EOF
+10.0.0.14 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/3/EL_9/aarch64/pdsh-ohpc-2.34-300.ohpc.1.5.aarch64.rpm HTTP/1.1" 200 10 "-" "-"
+10.0.0.15 - - [01/Mar/2019:10:00:00 +0000] "GET /dist/2.2/OpenHPC-2.2.Leap_15.aarch64.tar HTTP/1.1" 200 20 "-" "-"
+10.0.0.15 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/3/EL_9/x86_64/repodata/repomd.xml HTTP/1.1" 200 10 "-" "-"
+10.0.0.15 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/3/EL_9/noarch/ohpc-filesystem-3.0-300.ohpc.4.1.noarch.rpm HTTP/1.1" 200 10 "-" "-"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${ARCH_DEST}" "${ARCH_DEST}/access_log"
+ARCHITECTURES=$(jq -c '[.requests_per_architecture[] | select(.period == "2019") | [.architecture, .release, .requests, .unique, .size]]' "${ARCH_DEST}"/stats.json)
+if [ "${ARCHITECTURES}" != '[["x86_64",null,1,1,10],["x86_64","ohpc3",1,1,10],["aarch64",null,2,2,30],["aarch64","ohpc3",1,1,10],["noarch",null,1,1,10],["noarch","ohpc3",1,1,10]]' ]; then
+	echo "ERROR: requests per architecture returned unexpected values: ${ARCHITECTURES}"
+	jq '.' "${ARCH_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${ARCH_DEST}"
+
+# The timezone offset of the requests is honoured and requests are reported in --timezone
+TIMEZONE_DEST=$(mktemp -d)
+cat > "${TIMEZONE_DEST}/access_log" <<EOF
+10.0.0.10 - - [31/Dec/2019:23:30:00 -0100] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "-"
+10.0.0.11 - - [01/Jan/2020:00:30:00 +0200] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "-"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${TIMEZONE_DEST}" "${TIMEZONE_DEST}/access_log"
+YEARS=$(jq -c '[.unique_visitors_per_year[] | [.year, .overall]]' "${TIMEZONE_DEST}"/stats.json)
+if [ "${YEARS}" != "[[2019,1],[2020,1]]" ]; then
+	echo "ERROR: UTC timezone returned unexpected values: ${YEARS}"
+	exit 1
+fi
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${TIMEZONE_DEST}" --timezone -01:00 "${TIMEZONE_DEST}/access_log"
+YEARS=$(jq -c '[.unique_visitors_per_year[] | [.year, .overall]]' "${TIMEZONE_DEST}"/stats.json)
+if [ "${YEARS}" != "[[2019,2]]" ]; then
+	echo "ERROR: -01:00 timezone returned unexpected values: ${YEARS}"
+	exit 1
+fi
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${TIMEZONE_DEST}" --timezone Asia/Tokyo "${TIMEZONE_DEST}/access_log"
+YEARS=$(jq -c '[.unique_visitors_per_year[] | [.year, .overall]]' "${TIMEZONE_DEST}"/stats.json)
+if [ "${YEARS}" != "[[2020,2]]" ]; then
+	echo "ERROR: Asia/Tokyo timezone returned unexpected values: ${YEARS}"
+	exit 1
+fi
+rm -rf "${TIMEZONE_DEST}"
+
+# Only requests between --since and --until are counted
+RANGE_DEST=$(mktemp -d)
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${RANGE_DEST}" --since 2023-01-01 --until 2024-12-31 tests/access_log
+YEARS=$(jq -c '[.unique_visitors_per_year[] | [.year, .overall]]' "${RANGE_DEST}"/stats.json)
+if [ "${YEARS}" != "[[2023,21],[2024,25]]" ]; then
+	echo "ERROR: --since/--until returned unexpected values: ${YEARS}"
+	jq '.' "${RANGE_DEST}"/stats.json
+	exit 1
+fi
+# Lines outside of the date range are not counted requests
+COUNTED=$(jq -c '[.counted_requests, .outside_date_range_lines]' "${RANGE_DEST}"/stats.json)
+if [ "${COUNTED}" != "[266,27]" ]; then
+	echo "ERROR: --since/--until returned unexpected line counts: ${COUNTED}"
+	jq '.' "${RANGE_DEST}"/stats.json
+	exit 1
+fi
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${RANGE_DEST}" --since 2025-01-15T10:00:00Z tests/access_log
+YEARS=$(jq -c '[.unique_visitors_per_year[] | [.year, .overall]]' "${RANGE_DEST}"/stats.json)
+if [ "${YEARS}" != "[[2025,5]]" ]; then
+	echo "ERROR: --since returned unexpected values: ${YEARS}"
+	jq '.' "${RANGE_DEST}"/stats.json
+	exit 1
+fi
+# The whole file is before --since and is skipped without parsing every line
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${RANGE_DEST}" --since 2026-01-01 tests/access_log
+OUTSIDE=$(jq '.outside_date_range_lines' "${RANGE_DEST}"/stats.json)
+if [ "${OUTSIDE}" != "$(grep -c . tests/access_log)" ]; then
+	echo "ERROR: skipping chunks outside of the date range returned unexpected value: ${OUTSIDE}"
+	jq '.' "${RANGE_DEST}"/stats.json
+	exit 1
+fi
+# Midnight of 2024-09-08 does not exist in Chile, the day starts at 01:00
+cat > "${RANGE_DEST}/access_log" <<EOF
+10.0.0.1 - - [08/Sep/2024:03:59:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Rocky Linux 8.9; generic; Linux.x86_64)"
+10.0.0.2 - - [08/Sep/2024:04:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Rocky Linux 8.9; generic; Linux.x86_64)"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${RANGE_DEST}" --timezone America/Santiago --since 2024-09-08 \
+	"${RANGE_DEST}/access_log"
+COUNTED=$(jq -c '[.counted_requests, .outside_date_range_lines]' "${RANGE_DEST}"/stats.json)
+if [ "${COUNTED}" != "[1,1]" ]; then
+	echo "ERROR: --since on a day without midnight returned unexpected values: ${COUNTED}"
+	jq '.' "${RANGE_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${RANGE_DEST}"
+
+# --state counts only the new part of a growing log and recognizes the
+# log again after it has been rotated and compressed
+STATE_DEST=$(mktemp -d)
+LINES=$(wc -l < tests/access_log)
+head -n $((LINES / 2)) tests/access_log > "${STATE_DEST}/access_log"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${STATE_DEST}" --state "${STATE_DEST}/state.json" "${STATE_DEST}/access_log"
+tail -n +$((LINES / 2 + 1)) tests/access_log >> "${STATE_DEST}/access_log"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${STATE_DEST}" --state "${STATE_DEST}/state.json" "${STATE_DEST}/access_log"
+if ! diff <(jq -S '.' "${DEST}"/stats.json) <(jq -S '.' "${STATE_DEST}"/stats.json); then
+	echo "ERROR: incremental run with --state differs from a full run"
+	exit 1
+fi
+gzip "${STATE_DEST}/access_log"
+mv "${STATE_DEST}/access_log.gz" "${STATE_DEST}/access_log.1.gz"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${STATE_DEST}" --state "${STATE_DEST}/state.json" "${STATE_DEST}/access_log.1.gz"
+if ! diff <(jq -S '.' "${DEST}"/stats.json) <(jq -S '.' "${STATE_DEST}"/stats.json); then
+	echo "ERROR: rotated log was counted again with --state"
+	exit 1
+fi
+rm -rf "${STATE_DEST}"
+
+# A half-written last line is left for the next run with --state
+PARTIAL_DEST=$(mktemp -d)
+BYTES=$(head -n $((LINES / 2)) tests/access_log | wc -c)
+head -c $((BYTES + 50)) tests/access_log > "${PARTIAL_DEST}/access_log"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${PARTIAL_DEST}" --state "${PARTIAL_DEST}/state.json" "${PARTIAL_DEST}/access_log"
+tail -c +$((BYTES + 51)) tests/access_log >> "${PARTIAL_DEST}/access_log"
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${PARTIAL_DEST}" --state "${PARTIAL_DEST}/state.json" "${PARTIAL_DEST}/access_log"
+if ! diff <(jq -S '.' "${DEST}"/stats.json) <(jq -S '.' "${PARTIAL_DEST}"/stats.json); then
+	echo "ERROR: half-written line was counted before it was complete"
+	exit 1
+fi
+rm -rf "${PARTIAL_DEST}"
+
+# Results of multiple servers are merged and clients which used more than
+# one server are only counted once
+MERGE_DEST=$(mktemp -d)
+mkdir "${MERGE_DEST}/a" "${MERGE_DEST}/b"
+awk 'NR % 2 == 1' tests/access_log > "${MERGE_DEST}/a/access_log"
+awk 'NR % 2 == 0' tests/access_log > "${MERGE_DEST}/b/access_log"
+for HOST in a b; do
+	"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${MERGE_DEST}/${HOST}" --state "${MERGE_DEST}/${HOST}/state.json" "${MERGE_DEST}/${HOST}/access_log"
+done
+"${OHPC_LOG_ANALYZER}" merge --no-svg --output-directory "${MERGE_DEST}" --state "${MERGE_DEST}/merged.json" "${MERGE_DEST}/a/state.json" "${MERGE_DEST}/b/state.json"
+if ! diff <(jq -S '.' "${DEST}"/stats.json) <(jq -S '.' "${MERGE_DEST}"/stats.json); then
+	echo "ERROR: merged results of two servers differ from the results of one log"
+	exit 1
+fi
+if [ ! -e "${MERGE_DEST}/merged.json" ]; then
+	echo "ERROR: merged state not found"
+	exit 1
+fi
+rm -rf "${MERGE_DEST}"
+
+# analyze and report can run separately and query prints the same numbers
+# as the report
+SPLIT_DEST=$(mktemp -d)
+"${OHPC_LOG_ANALYZER}" analyze --state "${SPLIT_DEST}/state.json" tests/access_log
+"${OHPC_LOG_ANALYZER}" report --no-svg --output-directory "${SPLIT_DEST}" --state "${SPLIT_DEST}/state.json"
+if ! diff <(jq -S '.' "${DEST}"/stats.json) <(jq -S '.' "${SPLIT_DEST}"/stats.json); then
+	echo "ERROR: analyze and report differ from a single run"
+	exit 1
+fi
+if ! diff <(jq -S '.requests_per_week' "${DEST}"/stats.json) <("${OHPC_LOG_ANALYZER}" query --state "${SPLIT_DEST}/state.json" --by week --json | jq -S '.'); then
+	echo "ERROR: query returned unexpected requests per week"
+	exit 1
+fi
+DAYS=$("${OHPC_LOG_ANALYZER}" query --state "${SPLIT_DEST}/state.json" --by day --period 2024-04 | tail -n +2 | wc -l)
+if [ "${DAYS}" != "$(jq '[.requests_per_day[] | select(.period | startswith("2024-04"))] | length' "${DEST}"/stats.json)" ]; then
+	echo "ERROR: query --period returned unexpected number of days: ${DAYS}"
+	exit 1
+fi
+rm -rf "${SPLIT_DEST}"
+
+# Busiest clients per month, labeled or excluded by the networks of the configuration
+NETWORK_DEST=$(mktemp -d)
+cp ohpc-log-analyzer.toml "${NETWORK_DEST}/config.toml"
+cat >> "${NETWORK_DEST}/config.toml" <<EOF
+
+[[network]]
+name = "mirror"
+cidrs = ["192.0.2.0/24"]
+
+[[network]]
+name = "ci"
+cidrs = ["198.51.100.0/24", "2001:db8::/32"]
+exclude = true
+EOF
+cat > "${NETWORK_DEST}/access_log" <<EOF
+192.0.2.10 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 100 "-" "-"
+192.0.2.10 - - [01/Mar/2019:10:00:01 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 100 "-" "-"
+192.0.2.10 - - [01/Mar/2019:10:00:02 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 100 "-" "-"
+10.0.0.30 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/x86_64/pdsh-ohpc-2.34-2.1.ohpc.2.0.x86_64.rpm HTTP/1.1" 200 700 "-" "-"
+198.51.100.7 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 100 "-" "-"
+2001:db8::1 - - [01/Mar/2019:10:00:00 +0000] "GET /OpenHPC/2/EL_8/repodata/repomd.xml HTTP/1.1" 200 100 "-" "-"
+EOF
+"${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${NETWORK_DEST}" --config "${NETWORK_DEST}/config.toml" "${NETWORK_DEST}/access_log"
+CLIENTS=$(jq -c '[[.top_clients_by_requests[] | [.ip, .requests, .requests_share, .network]], .top_clients_by_size[0].ip]' "${NETWORK_DEST}"/stats.json)
+if [ "${CLIENTS}" != '[[["192.0.2.10",3,0.75,"mirror"],["10.0.0.30",1,0.25,null]],"10.0.0.30"]' ]; then
+	echo "ERROR: busiest clients returned unexpected values: ${CLIENTS}"
+	jq '.' "${NETWORK_DEST}"/stats.json
+	exit 1
+fi
+NETWORKS=$(jq -c '[.requests_per_network[] | select(.period == "2019") | [.network, .excluded, .requests, .unique]]' "${NETWORK_DEST}"/stats.json)
+if [ "${NETWORKS}" != '[["ci",true,2,2],["mirror",false,3,1]]' ]; then
+	echo "ERROR: requests per network returned unexpected values: ${NETWORKS}"
+	jq '.' "${NETWORK_DEST}"/stats.json
+	exit 1
+fi
+rm -rf "${NETWORK_DEST}"
+
+# Releases and distributions come from the configuration file
+CONFIG_DEST=$(mktemp -d)
+cp ohpc-log-analyzer.toml "${CONFIG_DEST}/config.toml"
+cat >> "${CONFIG_DEST}/config.toml" <<EOF
+
+[[release]]
+key = "ohpc5"
+name = "5.x"
+prefix = "/OpenHPC/5/"
+
+[[release.distribution]]
+name = "RHEL"
+markers = ["EL_11"]
+
+[[os_alias]]
+name = "AlmaLinux"
+patterns = ["^Alma ?Linux$"]
+EOF
+cat > "${CONFIG_DEST}/access_log" <<EOF
+10.0.0.12 - - [01/Mar/2026:10:00:00 +0000] "GET /OpenHPC/5/EL_11/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Alma Linux 11.0; generic; Linux.x86_64)"
+10.0.0.13 - - [01/Mar/2026:10:00:00 +0000] "GET /OpenHPC/4/EL_10/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Fedora Linux 43; generic; Linux.x86_64)"
+10.0.0.14 - - [01/Mar/2026:10:00:00 +0000] "GET /OpenHPC/4/EL_10/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Rocky Linux 10.0; generic; Linux.x86_64)"
+EOF
+OUTPUT=$("${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${CONFIG_DEST}" --config "${CONFIG_DEST}/config.toml" \
+	--state "${CONFIG_DEST}/state.json" "${CONFIG_DEST}/access_log")
+RELEASES=$(jq -c '.unique_visitors_per_year[0] | [.ohpc4, .ohpc5, .overall]' "${CONFIG_DEST}"/stats.json)
+if [ "${RELEASES}" != "[2,1,3]" ]; then
+	echo "ERROR: release from the configuration file returned unexpected values: ${RELEASES}"
+	jq '.' "${CONFIG_DEST}"/stats.json
+	exit 1
+fi
+# Operating system names are replaced by the aliases of the configuration,
+# names of aliases are not reported
+OS_NAMES=$(jq -c '[([.result_libdnf[].name] | sort), .unaliased_os_names]' "${CONFIG_DEST}"/stats.json)
+if [ "${OS_NAMES}" != '[["AlmaLinux","Fedora Linux","Rocky Linux"],{"Fedora Linux":"2026-03"}]' ]; then
+	echo "ERROR: operating system aliases returned unexpected values: ${OS_NAMES}"
+	jq '.' "${CONFIG_DEST}"/stats.json
+	exit 1
+fi
+# The state keeps the names of the user agents, aliases are applied to the report
+STATE_NAMES=$(jq -c '[.aggregator.libdnf_results[][1] | keys[]] | sort' "${CONFIG_DEST}/state.json")
+if [ "${STATE_NAMES}" != '["Alma Linux","Fedora Linux","Rocky Linux"]' ]; then
+	echo "ERROR: state file contains unexpected operating system names: ${STATE_NAMES}"
+	exit 1
+fi
+# Only names which are not in the loaded state are reported as new
+if echo "${OUTPUT}" | grep -q "New operating system name"; then
+	echo "ERROR: new operating system names were reported without a state: ${OUTPUT}"
+	exit 1
+fi
+cat > "${CONFIG_DEST}/access_log.new" <<EOF
+10.0.0.15 - - [01/Apr/2026:10:00:00 +0000] "GET /OpenHPC/4/EL_10/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Fedora Linux 43; generic; Linux.x86_64)"
+10.0.0.16 - - [01/Apr/2026:10:00:00 +0000] "GET /OpenHPC/4/EL_10/repodata/repomd.xml HTTP/1.1" 200 10 "-" "libdnf (Oracle Linux Server 10.0; generic; Linux.x86_64)"
+EOF
+NEW_NAMES=$("${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${CONFIG_DEST}" --config "${CONFIG_DEST}/config.toml" \
+	--state "${CONFIG_DEST}/state.json" "${CONFIG_DEST}/access_log.new" | grep "New operating system name")
+if [ "${NEW_NAMES}" != "New operating system name 'Oracle Linux Server' first seen in 2026-04" ]; then
+	echo "ERROR: unexpected new operating system names: ${NEW_NAMES}"
+	exit 1
+fi
+# A state file can only be used with the releases it was written with
+if "${OHPC_LOG_ANALYZER}" report --no-svg --output-directory "${CONFIG_DEST}" --state "${CONFIG_DEST}/state.json"; then
+	echo "ERROR: state file was loaded with different releases"
+	exit 1
+fi
+# Release keys must not clash with the other fields of the results
+sed 's/^key = "ohpc5"$/key = "overall"/' "${CONFIG_DEST}/config.toml" > "${CONFIG_DEST}/reserved.toml"
+if "${OHPC_LOG_ANALYZER}" --no-svg --output-directory "${CONFIG_DEST}" --config "${CONFIG_DEST}/reserved.toml" \
+	"${CONFIG_DEST}/access_log"; then
+	echo "ERROR: reserved release key was accepted"
+	exit 1
+fi
+rm -rf "${CONFIG_DEST}"
+
 jq '.' "${DEST}"/stats.json
 
 rm -rf "${DEST}"